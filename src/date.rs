@@ -0,0 +1,92 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A day on the calendar, as the number of days since 1970-01-01.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+	days: i32,
+}
+
+impl Date {
+	pub const fn from_days(days: i32) -> Self {
+		Self { days }
+	}
+
+	/// The date of a year, month (1 to 12) and day of the month (1 to 31).
+	pub const fn from_ymd(year: i32, month: u32, day: u32) -> Self {
+		// Howard Hinnant's `days_from_civil`, the other way around from `ymd`
+		let year = if month <= 2 { year - 1 } else { year };
+		let era = year.div_euclid(400);
+		let yoe = year.rem_euclid(400);
+		let mp = if month > 2 { month - 3 } else { month + 9 } as i32;
+		let doy = (153 * mp + 2) / 5 + day as i32 - 1;
+		let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+		Self::from_days(era * 146097 + doe - 719468)
+	}
+
+	/// Today's date in the local time zone, or in UTC if the system can't tell which time zone it's in.
+	pub fn today() -> Self {
+		let secs = (SystemTime::now().duration_since(UNIX_EPOCH)).map_or(0, |since_epoch| since_epoch.as_secs());
+
+		match local_ymd(secs) {
+			Some((year, month, day)) => Self::from_ymd(year, month, day),
+			None => Self::from_days((secs / 86400) as i32),
+		}
+	}
+
+	pub const fn days(&self) -> i32 {
+		self.days
+	}
+
+	/// Year, month (1 to 12) and day of the month (1 to 31) of the date.
+	pub const fn ymd(&self) -> (i32, u32, u32) {
+		// Howard Hinnant's `civil_from_days`, see https://howardhinnant.github.io/date_algorithms.html
+		let z = self.days + 719468;
+		let era = z.div_euclid(146097);
+		let doe = z.rem_euclid(146097);
+		let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+		let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+		let mp = (5 * doy + 2) / 153;
+		let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+		let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+		let year = yoe + era * 400 + (month <= 2) as i32;
+
+		(year, month, day)
+	}
+}
+
+impl fmt::Display for Date {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (year, month, day) = self.ymd();
+		write!(f, "{year:04}-{month:02}-{day:02}")
+	}
+}
+
+/// Year, month (1 to 12) and day of the month (1 to 31) in the local time zone, `secs` seconds after 1970-01-01 UTC.
+///
+/// The C library already knows about the time zone, so it's asked directly.
+#[cfg(unix)]
+fn local_ymd(secs: u64) -> Option<(i32, u32, u32)> {
+	let time = libc::time_t::try_from(secs).ok()?;
+	// SAFETY: `tm` is plain old data, which `localtime_r` fills in if it succeeds
+	let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+	let result = unsafe { libc::localtime_r(&time, &mut tm) };
+
+	(!result.is_null()).then(|| (tm.tm_year + 1900, tm.tm_mon as u32 + 1, tm.tm_mday as u32))
+}
+
+#[cfg(windows)]
+fn local_ymd(secs: u64) -> Option<(i32, u32, u32)> {
+	let time = libc::time_t::try_from(secs).ok()?;
+	// SAFETY: `tm` is plain old data, which `localtime_s` fills in if it succeeds
+	let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+	let error = unsafe { libc::localtime_s(&mut tm, &time) };
+
+	(error == 0).then(|| (tm.tm_year + 1900, tm.tm_mon as u32 + 1, tm.tm_mday as u32))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn local_ymd(_secs: u64) -> Option<(i32, u32, u32)> {
+	None
+}
@@ -1,11 +1,10 @@
 use std::error::Error;
 use std::num::NonZero;
+use std::path::Path;
 use std::rc::Rc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::snake::Direction;
-
-use self::math::pos::pos;
+use self::math::pos::{pos, Pos};
 use self::math::size::size;
 use self::render::bitmap::Bitmap;
 use self::render::color::{alphacomp, Color};
@@ -13,7 +12,11 @@ use image::{ImageFormat, ImageResult};
 use math::size::Size;
 use owo_colors::OwoColorize;
 use render::{DrawCommand, Renderer, Rotate, SpritesheetId};
-use snake::{Banana, SnaekSheet, SnakeGame};
+use snake::{
+	bot_by_name, Achievement, Banana, Border, Clock, DailyHistory, DeathCause, Difficulty, Direction, HighScore,
+	HighScores, Level, Mode, Replay, ReplayPlayer, RewindBuffer, Rules, SnaekRng, SnaekSheet, SnakeController,
+	SnakeGame, Stats, Tile, WallClock, BOT_NAMES, MAX_NAME_LEN,
+};
 use ui::{
 	Anchor, FlexDirection, Mouse, UiContext, WidgetDim, WidgetFlags, WidgetId, WidgetLayout, WidgetPadding,
 	WidgetProps, WidgetSize, WidgetSprite,
@@ -26,9 +29,12 @@ use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{CursorIcon, Icon, Theme, Window, WindowAttributes, WindowId};
 
+mod date;
 mod math;
 mod render;
+mod sim;
 mod snake;
+mod storage;
 mod ui;
 
 const SNAEK_APP_ICON: &[u8] = include_bytes!("../assets/icon.png");
@@ -36,6 +42,54 @@ const SNAEK_APP_ICON: &[u8] = include_bytes!("../assets/icon.png");
 const IMG_ASCII_CHARS: &[u8] = include_bytes!("../assets/ascii-chars.png");
 const IMG_SNAEKSHEET: &[u8] = include_bytes!("../assets/snaeksheet.png");
 
+/// Extension of level files. Any other file passed on the command line is read as a replay.
+const LEVEL_EXTENSION: &str = "snaeklevel";
+
+/// Replay of the last finished game, in the data directory.
+const LAST_REPLAY_FILE: &str = "last.snaekreplay";
+
+/// Game that was still going on when the window got closed, in the data directory.
+const SAVED_GAME_FILE: &str = "current.snaeksave";
+
+/// Name of the high score table in the data directory.
+const HIGH_SCORES_FILE: &str = "scores.snaekscores";
+
+/// Results of the daily challenges, in the data directory.
+const DAILY_HISTORY_FILE: &str = "daily.snaekdaily";
+
+/// Lifetime stats and achievements, in the data directory.
+const STATS_FILE: &str = "stats.snaekstats";
+
+/// Name that goes in the high score table when the player didn't type any.
+const DEFAULT_PLAYER_NAME: &str = "Player";
+
+/// How long a frame lasts while something moves on screen. The rest of the time, frames are only drawn when needed.
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// How many ticks the arrow keys skip in the replay viewer.
+const REPLAY_SEEK_TICKS: u64 = 10;
+
+/// How long the head of a dead snake blinks before the game over overlay shows up.
+const DEATH_ANIMATION_DURATION: Duration = Duration::from_millis(1200);
+
+/// How long the head of a dead snake stays visible, and then hidden, when blinking.
+const DEATH_BLINK_PERIOD: Duration = Duration::from_millis(150);
+
+/// Number of ticks a banana blinks for before going away.
+const BANANA_WARNING_TICKS: u64 = 12;
+
+/// Most bananas the rules can put on the playfield at once from the keyboard.
+const MAX_BANANAS: u8 = 5;
+
+/// Banana spawn delays to pick from with the keyboard, in ticks.
+const BANANA_SPAWN_DELAYS: [u32; 4] = [0, 5, 10, 20];
+
+/// How far back in time rewinding takes the game in practice mode.
+const REWIND_DURATION: Duration = Duration::from_secs(3);
+
+/// Number of ticks kept around for rewinding. That's enough to go back 3 seconds at over 100 tiles per second.
+const REWIND_BUFFER_LEN: usize = 320;
+
 /// Loads a PNG from memory into a raw ARGB8 bitmap.
 fn load_png_from_memory(png: &[u8]) -> ImageResult<Bitmap> {
 	let img = image::load_from_memory_with_format(png, ImageFormat::Png)?;
@@ -60,11 +114,20 @@ const SNAEK_PIXEL_SIZE: u32 = 4;
 const VIEWPORT_SIZE: Size = size(WIDTH, HEIGHT);
 const SNAEK_BLACK: Color = Color::from_hex(0xff181425);
 
+/// Masks that tell the snakes apart, as `(mask_and, mask_or)`. Player one keeps the original colors.
+const SNAKE_TINTS: [Option<(Color, Color)>; snake::MAX_SNAKES] =
+	[None, Some((Color::from_hex(0xffffb0ff), Color::from_hex(0x00400000)))];
+
 fn main() {
+	let mut args = std::env::args().skip(1).peekable();
+	if args.peek().is_some_and(|arg| arg == "sim") {
+		std::process::exit(sim::run(args.skip(1)));
+	}
+
 	eprintln!("{}", "Snaek!!".yellow());
 
 	let event_loop = EventLoop::new().unwrap();
-	event_loop.set_control_flow(ControlFlow::Poll);
+	event_loop.set_control_flow(ControlFlow::Wait);
 
 	let mut app = match App::new() {
 		Ok(app) => app,
@@ -75,11 +138,53 @@ fn main() {
 		}
 	};
 
+	if let Some(path) = std::env::args_os().nth(1) {
+		let path = Path::new(&path);
+
+		if path.extension().is_some_and(|ext| ext == LEVEL_EXTENSION) {
+			match Level::load(path) {
+				Ok(level) => {
+					app.levels.push(level);
+					app.start_level(app.levels.len() - 1);
+				}
+				Err(e) => {
+					eprintln!("{}", "Couldn't load the level :(".red());
+					eprintln!("-> {}", e);
+					std::process::exit(1);
+				}
+			}
+		} else {
+			match storage::load::<Replay>(path) {
+				Ok(replay) => app.replay_player = Some(ReplayPlayer::new(replay)),
+				Err(e) => {
+					eprintln!("{}", "Couldn't load the replay :(".red());
+					eprintln!("-> {}", e);
+					std::process::exit(1);
+				}
+			}
+		}
+	} else {
+		restore_saved_game(&mut app);
+	}
+
 	event_loop.run_app(&mut app).unwrap();
 
+	submit_high_score(&mut app.high_scores, &mut app.name_entry, &app.snake_game);
+	app.stats.record_game(&app.snake_game);
+	save_current_game(&app.snake_game);
+	storage::save_data(STATS_FILE, &app.stats);
+
 	eprintln!("{}", "See you next time :)".green())
 }
 
+/// A daily challenge being played.
+#[derive(Debug, Clone, Copy)]
+struct DailyAttempt {
+	date: date::Date,
+	/// Whether it's the first game of the day, the one that goes in the history.
+	scored: bool,
+}
+
 struct App {
 	window: Option<Rc<Window>>,
 	surface: Option<softbuffer::Surface<Rc<Window>, Rc<Window>>>,
@@ -89,17 +194,48 @@ struct App {
 	renderer: Renderer,
 	draw_cmds: Vec<DrawCommand>,
 	mouse: Mouse,
-	last_move: Instant,
+	clock: Clock,
+	/// When to draw the next frame even if nothing else happens, if ever.
+	next_frame: Option<Instant>,
 	window_size: PhysicalSize<u32>,
 	pixel_size: u32,
 
 	snaek_sheet_id: SpritesheetId,
 	snaek_sheet: SnaekSheet,
 	snake_game: SnakeGame,
+	replay_player: Option<ReplayPlayer>,
+	levels: Vec<Level>,
+	level_index: usize,
+	/// Bot that steers player one instead of the keyboard, if any.
+	autopilot: Option<Box<dyn SnakeController>>,
+
+	high_scores: HighScores,
+	/// Name being typed in for the high score table, if the game that just ended made it in.
+	name_entry: Option<String>,
+	/// Rank of the last score that went in the high score table, to highlight it.
+	new_high_score: Option<usize>,
+
+	daily_history: DailyHistory,
+	/// The daily challenge the current game is, if it's one.
+	daily: Option<DailyAttempt>,
+
+	stats: Stats,
+	/// Achievements the current game unlocked, to highlight them.
+	new_achievements: Vec<Achievement>,
+
+	/// Time since a snake died, while the death animation plays.
+	death_animation: Option<Duration>,
+
+	/// In practice mode, the game can be rewound, but doesn't count for high scores once it has been.
+	practice: bool,
+	rewind_buffer: RewindBuffer<SnaekRng>,
 
 	debug: bool,
+	/// Whether the snakes slide from one slot to the next in between ticks, rather than jumping.
+	smooth_movement: bool,
 	show_game_over: bool,
-	next_direction: Direction,
+	show_scores: bool,
+	show_stats: bool,
 }
 
 impl App {
@@ -115,8 +251,8 @@ impl App {
 		let mut renderer = Renderer::new(VIEWPORT_SIZE, ascii_bitmap);
 		let snaek_sheet_id = renderer.register_spritesheet(load_png_from_memory(IMG_SNAEKSHEET)?);
 
-		let snake_game = SnakeGame::new(size(11, 11));
-		let next_direction = snake_game.direction();
+		let levels = snake::builtin_levels();
+		let snake_game = SnakeGame::new(levels[0].clone(), Rules::default(), rand::random());
 
 		Ok(Self {
 			window: None,
@@ -127,21 +263,267 @@ impl App {
 			renderer,
 			draw_cmds: Vec::new(),
 			mouse: Mouse::default(),
-			last_move: Instant::now(),
+			clock: Clock::new(WallClock::default()),
+			next_frame: None,
 			window_size: PhysicalSize::default(),
 			pixel_size: SNAEK_PIXEL_SIZE,
 
 			snaek_sheet_id,
 			snaek_sheet: snake::snaek_sheet(),
 			snake_game,
+			replay_player: None,
+			levels,
+			level_index: 0,
+			autopilot: None,
+
+			high_scores: storage::load_data(HIGH_SCORES_FILE).unwrap_or_default(),
+			name_entry: None,
+			new_high_score: None,
+
+			daily_history: storage::load_data(DAILY_HISTORY_FILE).unwrap_or_default(),
+			daily: None,
+
+			stats: storage::load_data(STATS_FILE).unwrap_or_default(),
+			new_achievements: Vec::new(),
+
+			death_animation: None,
+
+			practice: false,
+			rewind_buffer: RewindBuffer::new(REWIND_BUFFER_LEN),
 
 			debug: false,
+			smooth_movement: true,
 			show_game_over: false,
-			next_direction,
+			show_scores: false,
+			show_stats: false,
 		})
 	}
 }
 
+impl App {
+	/// Starts a new game on one of the levels, keeping the current rules.
+	fn start_level(&mut self, level_index: usize) {
+		self.level_index = level_index;
+		self.start_game(self.snake_game.rules().clone());
+	}
+
+	/// Starts a new game on the current level with different rules.
+	///
+	/// There can't be more snakes than the level has room for, so some players might be left out.
+	fn start_game(&mut self, mut rules: Rules) {
+		let level = self.levels[self.level_index].clone();
+		rules.snakes = rules.snakes.min(level.starts.len() as u8);
+
+		submit_high_score(&mut self.high_scores, &mut self.name_entry, &self.snake_game);
+		self.stats.record_game(&self.snake_game);
+		self.abandon_daily();
+		self.snake_game = SnakeGame::new(level, rules, rand::random());
+		self.show_game_over = false;
+		self.death_animation = None;
+		self.rewind_buffer.clear();
+		self.new_achievements.clear();
+	}
+
+	/// Starts a new game on the same level with the same rules.
+	fn restart(&mut self) {
+		self.start_game(self.snake_game.rules().clone());
+	}
+
+	/// Starts today's daily challenge. Only the first game of the day counts, the others are just for fun.
+	///
+	/// Bots and practice mode would make the game not count, so they get turned off.
+	fn start_daily(&mut self) {
+		self.abandon_daily();
+
+		let date = date::Date::today();
+		let scored = self.daily_history.start(date);
+		if !scored {
+			self.daily_history.abandon(date);
+		}
+		storage::save_data(DAILY_HISTORY_FILE, &self.daily_history);
+
+		submit_high_score(&mut self.high_scores, &mut self.name_entry, &self.snake_game);
+		self.stats.record_game(&self.snake_game);
+		self.snake_game = SnakeGame::daily(date);
+		self.level_index = level_index(&mut self.levels, self.snake_game.level());
+		self.autopilot = None;
+		self.practice = false;
+		self.show_game_over = false;
+		self.death_animation = None;
+		self.rewind_buffer.clear();
+		self.daily = Some(DailyAttempt { date, scored });
+		self.new_achievements.clear();
+	}
+
+	/// Leaves the daily challenge being played. If it's the one that counts and it's not over yet, it's recorded as
+	/// abandoned so that the attempt stays used up.
+	fn abandon_daily(&mut self) {
+		let Some(daily) = self.daily.take().filter(|daily| daily.scored) else {
+			return;
+		};
+
+		if !self.snake_game.is_over() {
+			self.daily_history.abandon(daily.date);
+			storage::save_data(DAILY_HISTORY_FILE, &self.daily_history);
+		}
+	}
+
+	/// Puts the result of the daily challenge that just ended in the history, if it's the one that counts.
+	fn finish_daily(&mut self) {
+		let Some(daily) = self.daily.filter(|daily| daily.scored) else {
+			return;
+		};
+
+		// everyone plays the very same game, so getting help from a bot or a rewind wouldn't be fair
+		let score = match !self.snake_game.is_assisted() && self.snake_game.rewinds() == 0 {
+			true => self.snake_game.snakes()[0].score(),
+			false => 0,
+		};
+
+		self.daily_history.finish(daily.date, score, self.snake_game.duration());
+		storage::save_data(DAILY_HISTORY_FILE, &self.daily_history);
+	}
+
+	fn toggle_practice(&mut self) {
+		self.practice = !self.practice;
+		self.rewind_buffer.clear();
+	}
+
+	/// Takes the game a few seconds back in practice mode, even after dying.
+	fn rewind(&mut self) {
+		if self.practice && rewind_game(&mut self.snake_game, &mut self.rewind_buffer) {
+			self.show_game_over = false;
+			self.death_animation = None;
+			self.name_entry = None;
+		}
+	}
+
+	/// Lets the time elapsed since the last event pass, before handling the next one.
+	///
+	/// Doing it on every event rather than every frame keeps the time spent waiting while paused from leaking into
+	/// the game once it's resumed.
+	fn advance_time(&mut self) {
+		let dt = self.clock.delta();
+
+		match &mut self.replay_player {
+			Some(replay_player) => replay_player.advance(dt),
+			None => self.snake_game.advance(dt),
+		}
+
+		if let Some(elapsed) = &mut self.death_animation {
+			*elapsed += dt;
+		}
+	}
+
+	/// How long until something moves on screen by itself, if anything does.
+	fn next_frame_in(&self) -> Option<Duration> {
+		let running = match &self.replay_player {
+			Some(replay_player) => replay_player.is_playing() && !replay_player.is_finished(),
+			None => !self.snake_game.is_paused() && !self.snake_game.is_over(),
+		};
+
+		// the time display counts the milliseconds, so it moves every frame
+		let frame_wait = running.then(|| {
+			let time_until_tick = shown_game(&self.snake_game, &self.replay_player).time_until_tick();
+			FRAME_DURATION.min(time_until_tick)
+		});
+
+		let animation_wait = self.death_animation.map(|elapsed| {
+			let period = DEATH_BLINK_PERIOD.as_millis() as u64;
+			let time_until_blink = Duration::from_millis(period - elapsed.as_millis() as u64 % period);
+			time_until_blink.min(DEATH_ANIMATION_DURATION.saturating_sub(elapsed))
+		});
+
+		frame_wait.into_iter().chain(animation_wait).min()
+	}
+
+	/// Shows the game over overlay, asking for a name if the game made it in the high score table.
+	fn show_game_over(&mut self) {
+		self.show_game_over = true;
+
+		if self.made_high_score() {
+			self.name_entry = Some(String::new());
+		}
+	}
+
+	/// Starts a new game at the next difficulty.
+	fn next_difficulty(&mut self) {
+		let mut rules = self.snake_game.rules().clone();
+		Difficulty::of(&rules).unwrap_or_default().next().apply(&mut rules);
+
+		self.start_game(rules);
+	}
+
+	/// Starts a new game in the next mode.
+	fn next_mode(&mut self) {
+		let mut rules = self.snake_game.rules().clone();
+		rules.mode = rules.mode.next();
+
+		self.start_game(rules);
+	}
+
+	/// Starts a new game with one more banana on the playfield, or back to just one.
+	fn next_banana_count(&mut self) {
+		let mut rules = self.snake_game.rules().clone();
+		rules.bananas = rules.bananas % MAX_BANANAS + 1;
+
+		self.start_game(rules);
+	}
+
+	/// Starts a new game with bananas taking longer to show up, or back to right away.
+	fn next_banana_spawn_delay(&mut self) {
+		let mut rules = self.snake_game.rules().clone();
+		let next = (BANANA_SPAWN_DELAYS.iter())
+			.position(|&delay| delay == rules.banana_spawn_delay)
+			.map_or(0, |i| (i + 1) % BANANA_SPAWN_DELAYS.len());
+		rules.banana_spawn_delay = BANANA_SPAWN_DELAYS[next];
+
+		self.start_game(rules);
+	}
+
+	/// Opens or closes the high score table. The game waits while it's open.
+	fn toggle_scores(&mut self) {
+		self.show_scores = !self.show_scores;
+
+		if self.show_scores {
+			self.show_stats = false;
+			self.snake_game.pause();
+		} else {
+			self.new_high_score = None;
+		}
+	}
+
+	/// Opens or closes the stats screen. The game waits while it's open.
+	fn toggle_stats(&mut self) {
+		self.show_stats = !self.show_stats;
+
+		if self.show_stats {
+			self.show_scores = false;
+			self.snake_game.pause();
+		}
+	}
+
+	/// Whether the game that just ended goes in the high score table.
+	///
+	/// Only solo games played by hand at one of the difficulties count, and never rewound ones, since the table is about
+	/// how good the player is.
+	fn made_high_score(&self) -> bool {
+		let snakes = self.snake_game.snakes();
+		let rules = self.snake_game.rules();
+		let difficulty = Difficulty::of(rules);
+
+		// scores from the other modes, or with more or slower bananas, aren't comparable to classic ones
+		!self.snake_game.is_assisted()
+			&& self.snake_game.rewinds() == 0
+			&& rules.mode == Mode::Classic
+			&& rules.bananas == Rules::default().bananas
+			&& rules.banana_spawn_delay == Rules::default().banana_spawn_delay
+			&& difficulty.is_some()
+			&& snakes.len() == 1
+			&& self.high_scores.qualifies(snakes[0].score())
+	}
+}
+
 impl ApplicationHandler for App {
 	fn can_create_surfaces(&mut self, event_loop: &ActiveEventLoop) {
 		let win_attribs = WindowAttributes::default()
@@ -172,11 +554,22 @@ impl ApplicationHandler for App {
 			surface.resize(width, height).unwrap();
 		}
 
+		win.request_redraw();
+
 		self.surface = Some(surface);
 		self.window = Some(win);
 	}
 
 	fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+		self.advance_time();
+
+		// anything but a redraw is input that might change what's on screen
+		if !matches!(event, WindowEvent::RedrawRequested) {
+			if let Some(window) = &self.window {
+				window.request_redraw();
+			}
+		}
+
 		match event {
 			WindowEvent::CloseRequested => {
 				event_loop.exit();
@@ -198,6 +591,39 @@ impl ApplicationHandler for App {
 				self.mouse.y = y / self.pixel_size as f64;
 			}
 
+			WindowEvent::KeyboardInput {
+				event:
+					KeyEvent {
+						physical_key: PhysicalKey::Code(key_code),
+						text,
+						state: ElementState::Pressed,
+						..
+					},
+				..
+			} if self.name_entry.is_some() => {
+				let Some(name) = &mut self.name_entry else {
+					return;
+				};
+
+				match key_code {
+					KeyCode::Backspace => {
+						name.pop();
+					}
+					KeyCode::Enter | KeyCode::NumpadEnter => {
+						self.new_high_score =
+							submit_high_score(&mut self.high_scores, &mut self.name_entry, &self.snake_game);
+						self.show_game_over = false;
+						self.show_scores = true;
+					}
+					KeyCode::Escape => self.name_entry = None,
+					_ => {
+						let room = MAX_NAME_LEN.saturating_sub(name.chars().count());
+						let typed = text.iter().flat_map(|text| text.chars());
+						name.extend(typed.filter(|&c| is_name_char(c)).take(room));
+					}
+				}
+			}
+
 			WindowEvent::KeyboardInput {
 				event:
 					KeyEvent {
@@ -207,13 +633,77 @@ impl ApplicationHandler for App {
 						..
 					},
 				..
-			} => match key_code {
-				KeyCode::ArrowUp | KeyCode::KeyW => self.next_direction = Direction::Up,
-				KeyCode::ArrowRight | KeyCode::KeyD => self.next_direction = Direction::Right,
-				KeyCode::ArrowDown | KeyCode::KeyS => self.next_direction = Direction::Down,
-				KeyCode::ArrowLeft | KeyCode::KeyA => self.next_direction = Direction::Left,
-				_ => {}
-			},
+			} => {
+				let arrows_snake = self.snake_game.snakes().len() - 1;
+				let autopilot_on = self.autopilot.is_some();
+				let arrows_on = !(autopilot_on && arrows_snake == 0);
+
+				match &mut self.replay_player {
+					Some(replay_player) => match key_code {
+						KeyCode::Space => replay_player.toggle_playing(),
+						KeyCode::ArrowRight | KeyCode::KeyD => {
+							replay_player.seek(replay_player.game().ticks() + REPLAY_SEEK_TICKS)
+						}
+						KeyCode::ArrowLeft | KeyCode::KeyA => {
+							replay_player.seek(replay_player.game().ticks().saturating_sub(REPLAY_SEEK_TICKS))
+						}
+						KeyCode::Escape => self.replay_player = None,
+						_ => {}
+					},
+					None => match key_code {
+						// WASD is for player one, and the arrows are for player two if there is one, or player one
+						// again otherwise. Player one's keys do nothing while a bot is playing for them.
+						KeyCode::KeyW if !autopilot_on => self.snake_game.change_direction(0, Direction::Up),
+						KeyCode::KeyD if !autopilot_on => self.snake_game.change_direction(0, Direction::Right),
+						KeyCode::KeyS if !autopilot_on => self.snake_game.change_direction(0, Direction::Down),
+						KeyCode::KeyA if !autopilot_on => self.snake_game.change_direction(0, Direction::Left),
+						KeyCode::ArrowUp if arrows_on => self.snake_game.change_direction(arrows_snake, Direction::Up),
+						KeyCode::ArrowRight if arrows_on => {
+							self.snake_game.change_direction(arrows_snake, Direction::Right)
+						}
+						KeyCode::ArrowDown if arrows_on => {
+							self.snake_game.change_direction(arrows_snake, Direction::Down)
+						}
+						KeyCode::ArrowLeft if arrows_on => {
+							self.snake_game.change_direction(arrows_snake, Direction::Left)
+						}
+						KeyCode::KeyB => {
+							let mut rules = self.snake_game.rules().clone();
+							rules.border = match rules.border {
+								Border::Wrap => Border::Solid,
+								Border::Solid => Border::Wrap,
+							};
+
+							self.start_game(rules);
+						}
+						KeyCode::Digit1 | KeyCode::Digit2 => {
+							let mut rules = self.snake_game.rules().clone();
+							rules.snakes = if key_code == KeyCode::Digit1 { 1 } else { 2 };
+
+							self.start_game(rules);
+						}
+						KeyCode::KeyL => self.start_level((self.level_index + 1) % self.levels.len()),
+						KeyCode::KeyG => self.next_difficulty(),
+						KeyCode::KeyM => self.next_mode(),
+						KeyCode::KeyN => self.next_banana_count(),
+						KeyCode::KeyK => self.next_banana_spawn_delay(),
+						KeyCode::KeyC => self.start_daily(),
+						KeyCode::KeyT => self.toggle_practice(),
+						KeyCode::KeyR => self.rewind(),
+						KeyCode::KeyP => toggle_pause(&mut self.snake_game),
+						KeyCode::KeyO => self.autopilot = next_autopilot(&self.autopilot),
+						KeyCode::KeyH => self.toggle_scores(),
+						KeyCode::Escape if self.show_scores => self.toggle_scores(),
+						KeyCode::KeyI => self.toggle_stats(),
+						KeyCode::Escape if self.show_stats => self.toggle_stats(),
+						KeyCode::KeyV => self.smooth_movement = !self.smooth_movement,
+						KeyCode::F3 => self.debug = !self.debug,
+						_ => {}
+					},
+				}
+			}
+
+			WindowEvent::Focused(false) => self.snake_game.pause(),
 
 			WindowEvent::Resized(PhysicalSize { width, height }) => {
 				let width = width.max(WIDTH as u32 * self.pixel_size);
@@ -238,6 +728,45 @@ impl ApplicationHandler for App {
 					return;
 				};
 
+				if self.replay_player.is_none() {
+					while self.snake_game.tick_due() {
+						if let Some(autopilot) = &mut self.autopilot {
+							let direction = autopilot.next_direction(&self.snake_game, 0);
+							self.snake_game.change_direction(0, direction);
+							self.snake_game.mark_assisted();
+						}
+
+						if self.practice {
+							self.rewind_buffer.push(&self.snake_game);
+						}
+
+						let tick_duration = self.snake_game.tick_duration();
+						self.snake_game.update();
+
+						// what bots do isn't something the player did
+						if self.autopilot.is_none() {
+							let unlocked = self.stats.record_tick(&self.snake_game, tick_duration);
+							self.new_achievements.extend(unlocked);
+						}
+
+						if self.snake_game.is_over() {
+							save_last_replay(&self.snake_game);
+							self.finish_daily();
+							storage::save_data(STATS_FILE, &self.stats);
+
+							match self.snake_game.is_dead() {
+								true => self.death_animation = Some(Duration::ZERO),
+								false => self.show_game_over(),
+							}
+						}
+					}
+				}
+
+				if (self.death_animation).is_some_and(|elapsed| elapsed >= DEATH_ANIMATION_DURATION) {
+					self.death_animation = None;
+					self.show_game_over();
+				}
+
 				self.draw_cmds.clear();
 				self.draw_cmds.push(DrawCommand::Clear);
 
@@ -248,25 +777,14 @@ impl ApplicationHandler for App {
 				self.ui.solve_layout();
 				self.ui.draw_widgets(&mut self.draw_cmds);
 				self.ui.free_untouched_widgets();
-				self.ui.react(&self.mouse);
-
-				let now = Instant::now();
-				self.snake_game.update_duration();
-
-				if (now - self.last_move).as_secs_f64() >= (1.0 / self.snake_game.speed() as f64) {
-					let was_dead = self.snake_game.is_dead();
 
-					self.snake_game.change_direction(self.next_direction);
-					self.snake_game.update();
-					self.next_direction = self.snake_game.direction();
-
-					if self.snake_game.is_dead() && !was_dead {
-						self.show_game_over = true;
-					}
-
-					self.last_move = now;
+				// the widgets only show how they react to the mouse once the UI is built again
+				if self.ui.react(&self.mouse) {
+					window.request_redraw();
 				}
 
+				self.next_frame = self.next_frame_in().map(|wait| Instant::now() + wait);
+
 				self.renderer.draw(&self.draw_cmds);
 
 				if let Some(surface) = &mut self.surface {
@@ -292,12 +810,210 @@ impl ApplicationHandler for App {
 
 					buffer.present().unwrap();
 				}
-
-				window.request_redraw();
 			}
 			_ => (),
 		}
 	}
+
+	fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+		match self.next_frame {
+			Some(next_frame) if next_frame <= Instant::now() => {
+				self.next_frame = None;
+				if let Some(window) = &self.window {
+					window.request_redraw();
+				}
+
+				event_loop.set_control_flow(ControlFlow::Wait);
+			}
+			Some(next_frame) => event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame)),
+			None => event_loop.set_control_flow(ControlFlow::Wait),
+		}
+	}
+}
+
+/// What the game over overlay says about how the snakes died, if they did.
+fn death_message(snake_game: &SnakeGame) -> Option<String> {
+	let snakes = snake_game.snakes();
+	let mut dead = snakes.iter().enumerate().filter(|(_, snake)| snake.is_dead());
+
+	let (i, snake) = dead.next()?;
+	if dead.next().is_some() {
+		return Some("Both crashed".to_owned());
+	}
+
+	let message = match (snakes.len(), snake.death_cause()?) {
+		(1, DeathCause::SelfCollision) => "You bit yourself".to_owned(),
+		(1, DeathCause::Wall) => "You hit a wall".to_owned(),
+		(1, DeathCause::OtherSnake) => "You hit a snake".to_owned(),
+		(_, DeathCause::SelfCollision) => format!("P{} bit itself", i + 1),
+		(_, DeathCause::Wall) => format!("P{} hit a wall", i + 1),
+		(_, DeathCause::OtherSnake) => format!("P{} hit P{}", i + 1, 2 - i),
+	};
+
+	Some(message)
+}
+
+/// What the game over overlay says about how the game ended.
+fn game_over_title(snake_game: &SnakeGame) -> &'static str {
+	let snakes = snake_game.snakes();
+
+	if snakes.len() == 1 {
+		return match (snake_game.is_won(), snake_game.is_time_up()) {
+			(true, _) => "You won! :D",
+			(false, true) => "Time's up!",
+			(false, false) => "Game Over! :(",
+		};
+	}
+
+	// Whoever is still alive wins. If the board got filled or the time ran out, the best score wins instead.
+	let (p1, p2) = match snake_game.is_won() || snake_game.is_time_up() {
+		true => (snakes[0].score(), snakes[1].score()),
+		false => (!snakes[0].is_dead() as u32, !snakes[1].is_dead() as u32),
+	};
+
+	match p1.cmp(&p2) {
+		std::cmp::Ordering::Greater => "P1 wins!",
+		std::cmp::Ordering::Less => "P2 wins!",
+		std::cmp::Ordering::Equal => "Draw!",
+	}
+}
+
+/// Goes from human control to each of the bots in turn, then back to human control.
+fn next_autopilot(autopilot: &Option<Box<dyn SnakeController>>) -> Option<Box<dyn SnakeController>> {
+	let next = match autopilot {
+		Some(autopilot) => BOT_NAMES.iter().position(|&name| name == autopilot.name())? + 1,
+		None => 0,
+	};
+
+	BOT_NAMES.get(next).and_then(|&name| bot_by_name(name))
+}
+
+fn toggle_pause(snake_game: &mut SnakeGame) {
+	if snake_game.is_paused() {
+		snake_game.resume();
+	} else if !snake_game.is_over() {
+		snake_game.pause();
+	}
+}
+
+/// Writes the replay of a finished game to the data directory, so it can be watched again or attached to a bug report.
+fn save_last_replay(snake_game: &SnakeGame) {
+	let Some(replay) = snake_game.replay() else {
+		return;
+	};
+
+	storage::save_data(LAST_REPLAY_FILE, &replay);
+}
+
+/// Takes the game back by [`REWIND_DURATION`], paused so that the player has time to get ready. Returns whether
+/// there was anything to go back to.
+fn rewind_game(snake_game: &mut SnakeGame, rewind_buffer: &mut RewindBuffer<SnaekRng>) -> bool {
+	if !rewind_buffer.rewind(snake_game, REWIND_DURATION) {
+		return false;
+	}
+
+	snake_game.pause();
+	true
+}
+
+/// Characters that can go in a name in the high score table. The font doesn't have anything beyond ASCII.
+fn is_name_char(c: char) -> bool {
+	c.is_ascii_alphanumeric() || " -_.!?".contains(c)
+}
+
+/// Puts the game that just ended in the high score table under the name being typed in, if there is one, and saves
+/// the table. Returns the rank the game got.
+fn submit_high_score(
+	high_scores: &mut HighScores,
+	name_entry: &mut Option<String>,
+	snake_game: &SnakeGame,
+) -> Option<usize> {
+	let name = name_entry.take()?;
+	let name = match name.trim() {
+		"" => DEFAULT_PLAYER_NAME.to_owned(),
+		name => name.to_owned(),
+	};
+
+	let rank = high_scores.insert(HighScore {
+		name,
+		score: snake_game.snakes()[0].score(),
+		duration: snake_game.duration(),
+		size: snake_game.size(),
+		date: date::Date::today(),
+		difficulty: Difficulty::of(snake_game.rules()).unwrap_or_default(),
+		level: snake_game.level().name.clone(),
+		border: snake_game.rules().border,
+	})?;
+
+	storage::save_data(HIGH_SCORES_FILE, high_scores);
+
+	Some(rank)
+}
+
+/// Picks up the game that was going on the last time the window got closed, if there was one.
+///
+/// The game comes back paused, so that it doesn't start running before the player is ready.
+fn restore_saved_game(app: &mut App) {
+	let Some(mut snake_game) = storage::load_data::<SnakeGame>(SAVED_GAME_FILE) else {
+		return;
+	};
+
+	snake_game.pause();
+
+	// a daily challenge that got interrupted can only go on if it's still the same day
+	let today = date::Date::today();
+	if snake_game.is_daily(today) {
+		let scored = (app.daily_history.result(today)).is_some_and(|result| !result.finished);
+		app.daily = Some(DailyAttempt { date: today, scored });
+	}
+
+	app.level_index = level_index(&mut app.levels, snake_game.level());
+	app.snake_game = snake_game;
+}
+
+/// Index of a level in the list, which gets added to it if it isn't there yet.
+fn level_index(levels: &mut Vec<Level>, level: &Level) -> usize {
+	match levels.iter().position(|other| other == level) {
+		Some(level_index) => level_index,
+		None => {
+			levels.push(level.clone());
+			levels.len() - 1
+		}
+	}
+}
+
+/// What the game over overlay says about the daily challenge that just ended.
+fn daily_message(daily: DailyAttempt, daily_history: &DailyHistory) -> Option<String> {
+	let result = daily_history.result(daily.date)?;
+
+	Some(match (daily.scored, daily_history.streak(daily.date)) {
+		(true, 1) => "Streak: 1 day".to_owned(),
+		(true, streak) => format!("Streak: {streak} days"),
+		(false, _) => format!("Today's score: {}", result.score),
+	})
+}
+
+/// What the game over overlay says about the achievements the game unlocked, if any. There's only room for one, so
+/// it's the last one.
+fn achievement_message(new_achievements: &[Achievement]) -> Option<String> {
+	let achievement = new_achievements.last()?;
+	Some(format!("New: {}", achievement.name()))
+}
+
+/// Keeps the current game for next time, or forgets about the saved one if the game is over.
+fn save_current_game(snake_game: &SnakeGame) {
+	match snake_game.is_over() {
+		true => storage::remove_data::<SnakeGame>(SAVED_GAME_FILE),
+		false => storage::save_data(SAVED_GAME_FILE, snake_game),
+	}
+}
+
+/// The game to show on the playfield: the replay being watched if there is one, the live game otherwise.
+fn shown_game<'a>(snake_game: &'a SnakeGame, replay_player: &'a Option<ReplayPlayer>) -> &'a SnakeGame {
+	match replay_player {
+		Some(replay_player) => replay_player.game(),
+		None => snake_game,
+	}
 }
 
 fn snaek_ui(app: &mut App, window: &Window) -> bool {
@@ -306,17 +1022,35 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 	let App {
 		ui,
 		renderer,
+		mouse,
 		snaek_sheet_id,
 		snaek_sheet,
 		snake_game,
+		replay_player,
+		autopilot,
+		high_scores,
+		name_entry,
+		new_high_score,
+		daily_history,
+		daily,
+		stats,
+		new_achievements,
+		death_animation,
+		practice,
+		rewind_buffer,
 		debug,
+		smooth_movement,
 		show_game_over,
-		next_direction,
+		show_scores,
+		show_stats,
 		..
 	} = app;
 
 	let snaek_sheet_id = *snaek_sheet_id;
 
+	// starting a new game needs the whole app, so it waits until the UI is built
+	let mut new_game: Option<fn(&mut App)> = None;
+
 	// UI
 	let window_frame = ui.build_widget(
 		WidgetProps::new(wk!())
@@ -351,8 +1085,21 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 					.with_padding(WidgetPadding::hv(2, 1)),
 			);
 			{
+				let mode = Some(snake_game.rules().mode).filter(|&mode| mode != Mode::Classic);
+				let difficulty =
+					Difficulty::of(snake_game.rules()).filter(|&difficulty| difficulty != Difficulty::Normal);
+
+				// there's only room for one of them
+				let title = match (autopilot.as_ref(), mode, difficulty) {
+					(Some(autopilot), _, _) => format!("Snaek ({})", autopilot.name()),
+					(None, _, _) if *practice => "Snaek (Practice)".to_owned(),
+					(None, _, _) if daily.is_some() => "Snaek (Daily)".to_owned(),
+					(None, Some(mode), _) => format!("Snaek ({})", mode.name()),
+					(None, None, Some(difficulty)) => format!("Snaek ({})", difficulty.name()),
+					(None, None, None) => "Snaek".to_owned(),
+				};
 				let title = ui.build_widget(
-					WidgetProps::text(wk!(), renderer.text("Snaek"))
+					WidgetProps::text(wk!(), renderer.text(title))
 						.with_anchor_origin(Anchor::BOTTOM_LEFT, Anchor::BOTTOM_LEFT)
 						.with_mask_and(Some(SNAEK_BLACK)),
 				);
@@ -381,7 +1128,7 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 		if navbar.start_pressed() {
 			cursor_icon = CursorIcon::Grabbing;
 			window.drag_window().unwrap();
-			app.mouse.reset_pressed();
+			mouse.reset_pressed();
 		}
 
 		let game_frame = ui.build_widget(
@@ -400,7 +1147,7 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 			{
 				let big_display = ui.big_3digits_display(
 					wk!(),
-					snake_game.bananas_eaten() as usize,
+					shown_game(snake_game, replay_player).snakes()[0].score().min(999) as usize,
 					snaek_sheet_id,
 					snaek_sheet.box_num_display,
 					snaek_sheet.bignum_placeholder,
@@ -430,15 +1177,23 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 					ui.add_child(middle_frame.id(), btn_restart.id());
 
 					if btn_restart.clicked() {
-						snake_game.restart();
-						*show_game_over = false;
-						*next_direction = snake_game.direction();
+						match replay_player {
+							Some(replay_player) => replay_player.seek(0),
+							None => new_game = Some(App::restart),
+						}
+					}
+
+					// right click to start over at the next difficulty
+					if btn_restart.r_clicked() && replay_player.is_none() {
+						new_game = Some(App::next_difficulty);
 					}
 
 					let icon_playpause = {
-						let sprite = match debug {
-							true => snaek_sheet.icon_play,
-							false => snaek_sheet.icon_debug,
+						let sprite = match replay_player {
+							Some(replay_player) if replay_player.is_playing() => snaek_sheet.icon_debug,
+							Some(_) => snaek_sheet.icon_play,
+							None if snake_game.is_paused() => snaek_sheet.icon_play,
+							None => snaek_sheet.icon_debug,
 						};
 
 						ui.build_widget(
@@ -447,7 +1202,7 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 								.with_acf(Some(alphacomp::xor)),
 						)
 					};
-					let btn_playdebug = ui.btn_box(
+					let btn_playpause = ui.btn_box(
 						WidgetProps::new(wk!())
 							.with_size(WidgetSize::hug())
 							.with_padding(WidgetPadding::hv(3, 2)),
@@ -455,10 +1210,18 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 						WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
 						icon_playpause.id(),
 					);
-					ui.add_child(middle_frame.id(), btn_playdebug.id());
+					ui.add_child(middle_frame.id(), btn_playpause.id());
+
+					if btn_playpause.clicked() {
+						match replay_player {
+							Some(replay_player) => replay_player.toggle_playing(),
+							None => toggle_pause(snake_game),
+						}
+					}
 
-					if btn_playdebug.clicked() {
-						*debug = !*debug;
+					// right click to hand player one over to a bot
+					if btn_playpause.r_clicked() && replay_player.is_none() {
+						*autopilot = next_autopilot(autopilot);
 					}
 				}
 				ui.add_child(display_frame.id(), middle_frame.id());
@@ -471,18 +1234,64 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 				{
 					let text_holder = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
 					{
-						let text = ui.build_widget(
-							WidgetProps::text(wk!(), renderer.text("Speykious"))
-								.with_anchor_origin(Anchor::BOTTOM_RIGHT, Anchor::BOTTOM_RIGHT)
-								.with_mask_and(Some(SNAEK_BLACK)),
-						);
-						ui.add_child(text_holder.id(), text.id());
+						let label = match replay_player {
+							Some(replay_player) if replay_player.is_desynced() => Some("Desync!"),
+							Some(_) => Some("Replay"),
+							None => None,
+						};
+
+						if let Some(label) = label {
+							let text = ui.build_widget(
+								WidgetProps::text(wk!(), renderer.text(label))
+									.with_anchor_origin(Anchor::BOTTOM_RIGHT, Anchor::BOTTOM_RIGHT)
+									.with_mask_and(Some(SNAEK_BLACK)),
+							);
+							ui.add_child(text_holder.id(), text.id());
+						} else {
+							// The big display shows the score of player one. Next to it goes either the score of
+							// player two, or the bananas eaten by player one if they're alone.
+							let snakes = shown_game(snake_game, replay_player).snakes();
+							let (icon_props, count) = match snakes.get(1) {
+								Some(player_two) => {
+									let (mask_and, mask_or) = SNAKE_TINTS[1].unzip();
+									let props =
+										WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.snake_head)
+											.with_mask_and(mask_and)
+											.with_mask_or(mask_or);
+									(props, player_two.score())
+								}
+								None => {
+									let props =
+										WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.banana_yellow);
+									(props, snakes[0].bananas_eaten())
+								}
+							};
+
+							let counter = ui.build_widget(
+								WidgetProps::new(wk!())
+									.with_anchor_origin(Anchor::BOTTOM_RIGHT, Anchor::BOTTOM_RIGHT)
+									.with_size(WidgetSize::hug())
+									.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 1)),
+							);
+							{
+								let icon = ui.build_widget(icon_props);
+								ui.add_child(counter.id(), icon.id());
+
+								let text = ui.build_widget(
+									WidgetProps::text(wk!(), renderer.text(count.to_string()))
+										.with_anchor_origin(Anchor::BOTTOM_LEFT, Anchor::BOTTOM_LEFT)
+										.with_mask_and(Some(SNAEK_BLACK)),
+								);
+								ui.add_child(counter.id(), text.id());
+							}
+							ui.add_child(text_holder.id(), counter.id());
+						}
 					}
 					ui.add_child(right_frame.id(), text_holder.id());
 
 					let time_display = ui.time_display(
 						wk!(),
-						snake_game.duration(),
+						shown_game(snake_game, replay_player).duration(),
 						snaek_sheet_id,
 						snaek_sheet.box_num_display,
 						snaek_sheet.num_colon,
@@ -503,25 +1312,139 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 						.with_padding(WidgetPadding::all(4)),
 				);
 				{
-					let container_size = snake_game.size() * 7;
+					let container_size = shown_game(snake_game, replay_player).size() * 7;
+
+					let mut container_props = WidgetProps::new(wk!())
+						.with_flags(WidgetFlags::DRAW_BACKGROUND)
+						.with_color(Color::from_hex(0xff262b44))
+						.with_size(WidgetSize::fixed(container_size.w, container_size.h));
+
+					// solid walls kill the snake, so we make them stand out
+					if shown_game(snake_game, replay_player).rules().border == Border::Solid {
+						container_props = container_props
+							.with_flags(WidgetFlags::DRAW_BACKGROUND | WidgetFlags::DRAW_BORDER)
+							.with_border_color(Color::from_hex(0xff8b9bb4))
+							.with_border_width(1);
+					}
 
-					let snake_container = ui.build_widget(
-						WidgetProps::new(wk!())
-							.with_flags(WidgetFlags::DRAW_BACKGROUND)
-							.with_color(Color::from_hex(0xff262b44))
-							.with_size(WidgetSize::fixed(container_size.w, container_size.h)),
-					);
+					let snake_container = ui.build_widget(container_props);
 					{
+						// the heads of dead snakes blink while the death animation plays
+						let hide_dead_heads = replay_player.is_none()
+							&& death_animation
+								.is_some_and(|elapsed| (elapsed.as_millis() / DEATH_BLINK_PERIOD.as_millis()) % 2 == 1);
+
+						// a replay that reached its end doesn't get to its next tick either
+						let tick_progress = match replay_player {
+							_ if !*smooth_movement => 1.0,
+							Some(replay_player) if replay_player.is_finished() => 1.0,
+							_ => shown_game(snake_game, replay_player).tick_progress(),
+						};
+
 						snaek_playfield(
-							snake_game,
+							shown_game(snake_game, replay_player),
 							ui,
-							renderer,
 							snake_container.id(),
 							snaek_sheet_id,
 							snaek_sheet,
+							tick_progress,
+							hide_dead_heads,
 							*debug,
-							show_game_over,
 						);
+
+						if let Some(player) = replay_player {
+							let exit = snaek_replay_controls(
+								player,
+								ui,
+								mouse,
+								snake_container.id(),
+								container_size.w,
+								snaek_sheet_id,
+								snaek_sheet,
+							);
+
+							if exit {
+								*replay_player = None;
+							}
+						} else if *show_scores {
+							let close = snaek_high_scores(
+								high_scores,
+								*new_high_score,
+								ui,
+								renderer,
+								snake_container.id(),
+								snaek_sheet_id,
+								snaek_sheet,
+							);
+
+							if close {
+								*show_scores = false;
+								*new_high_score = None;
+							}
+						} else if *show_stats {
+							let close = snaek_stats(
+								stats,
+								new_achievements,
+								ui,
+								renderer,
+								snake_container.id(),
+								snaek_sheet_id,
+								snaek_sheet,
+							);
+
+							if close {
+								*show_stats = false;
+							}
+						} else if *show_game_over {
+							let choice = snaek_game_over(
+								game_over_title(snake_game),
+								(daily.and_then(|daily| daily_message(daily, daily_history)))
+									.or_else(|| achievement_message(new_achievements))
+									.or_else(|| death_message(snake_game)),
+								name_entry.as_deref(),
+								*practice && snake_game.is_dead(),
+								ui,
+								renderer,
+								snake_container.id(),
+								snaek_sheet_id,
+								snaek_sheet,
+							);
+
+							match choice {
+								Some(GameOverChoice::Dismiss) => *show_game_over = false,
+								Some(GameOverChoice::SubmitName) => {
+									*new_high_score = submit_high_score(high_scores, name_entry, snake_game);
+									*show_game_over = false;
+									*show_scores = true;
+								}
+								Some(GameOverChoice::WatchReplay) => {
+									submit_high_score(high_scores, name_entry, snake_game);
+									*show_game_over = false;
+									*replay_player = snake_game.replay().map(ReplayPlayer::new);
+								}
+								Some(GameOverChoice::Rewind) if rewind_game(snake_game, rewind_buffer) => {
+									*show_game_over = false;
+									*name_entry = None;
+								}
+								Some(GameOverChoice::Rewind) | None => {}
+							}
+						} else if snake_game.is_paused() {
+							let choice = snaek_paused(
+								snake_game.rules().mode,
+								Difficulty::of(snake_game.rules()),
+								ui,
+								renderer,
+								snake_container.id(),
+								snaek_sheet_id,
+								snaek_sheet,
+							);
+
+							match choice {
+								Some(PausedChoice::NextMode) => new_game = Some(App::next_mode),
+								Some(PausedChoice::NextDifficulty) => new_game = Some(App::next_difficulty),
+								None => {}
+							}
+						}
 					}
 					ui.add_child(playfield.id(), snake_container.id());
 				}
@@ -533,19 +1456,28 @@ fn snaek_ui(app: &mut App, window: &Window) -> bool {
 	}
 
 	window.set_cursor(cursor_icon);
+
+	if let Some(new_game) = new_game {
+		new_game(app);
+	}
+
 	false
 }
 
+/// The playfield with everything on it.
+///
+/// Until the next tick, the heads and the tails of the snakes are drawn on their way from the slots they were in
+/// before the last one, `tick_progress` of the way through. At 1, they're drawn right in their slots.
 #[allow(clippy::too_many_arguments)]
 fn snaek_playfield(
 	snake_game: &SnakeGame,
 	ui: &mut UiContext,
-	renderer: &Renderer,
 	container_id: WidgetId,
 	snaek_sheet_id: SpritesheetId,
 	snaek_sheet: &SnaekSheet,
+	tick_progress: f32,
+	hide_dead_heads: bool,
 	debug: bool,
-	show_game_over: &mut bool,
 ) {
 	let playfield_size = snake_game.size();
 	for y in 0..playfield_size.h as i16 {
@@ -568,68 +1500,32 @@ fn snaek_playfield(
 
 			let sprite_holder = ui.build_widget(holder_props);
 			{
-				if let Some(banana) = slot.banana() {
-					let banana_sprite = match banana {
-						Banana::Yellow => snaek_sheet.banana_yellow,
-						Banana::Red => snaek_sheet.banana_red,
-						Banana::Cyan => snaek_sheet.banana_cyan,
-					};
-
+				if slot.tile() == Tile::Wall {
 					let sprite = ui.build_widget(
-						WidgetProps::simple_sprite(wk!(), snaek_sheet_id, banana_sprite)
+						WidgetProps::simple_sprite(wk!(ikey_x, ikey_y), snaek_sheet_id, snaek_sheet.wall)
 							.with_anchor_origin(Anchor::CENTER, Anchor::CENTER),
 					);
 					ui.add_child(sprite_holder.id(), sprite.id());
 				}
 
-				let is_straight = slot.direction_next() == slot.direction_prev().opposite();
+				// bananas that are about to go away blink, showing up every other tick
+				let despawn_tick = (snake_game.bananas().iter())
+					.find(|placed| placed.pos == slot_pos)
+					.and_then(|placed| placed.despawn_tick);
+				let blinked_out = despawn_tick.is_some_and(|despawn_tick| {
+					let ticks_left = despawn_tick.saturating_sub(snake_game.ticks());
+					ticks_left <= BANANA_WARNING_TICKS && ticks_left % 2 == 1
+				});
 
-				let snake_sprite = match (slot.has_snake_head(), slot.has_snake_tail()) {
-					(true, true) if is_straight => {
-						let rotate = match slot.direction_next() {
-							Direction::Up => Rotate::R270,
-							Direction::Right => Rotate::R0,
-							Direction::Down => Rotate::R90,
-							Direction::Left => Rotate::R180,
-						};
-						Some((snaek_sheet.snake_straight, rotate))
-					}
-					(true, true) => {
-						use Direction as D;
-						let rotate = match (slot.direction_next(), slot.direction_prev()) {
-							(D::Up, D::Right) | (D::Right, D::Up) => Rotate::R270,
-							(D::Right, D::Down) | (D::Down, D::Right) => Rotate::R0,
-							(D::Down, D::Left) | (D::Left, D::Down) => Rotate::R90,
-							(D::Left, D::Up) | (D::Up, D::Left) => Rotate::R180,
-							_ => Rotate::R0,
-						};
-						Some((snaek_sheet.snake_gay, rotate))
-					}
-					(true, false) => {
-						let rotate = match slot.direction_prev() {
-							Direction::Up => Rotate::R90,
-							Direction::Right => Rotate::R180,
-							Direction::Down => Rotate::R270,
-							Direction::Left => Rotate::R0,
-						};
-						Some((snaek_sheet.snake_head, rotate))
-					}
-					(false, true) => {
-						let rotate = match slot.direction_next() {
-							Direction::Up => Rotate::R0,
-							Direction::Right => Rotate::R90,
-							Direction::Down => Rotate::R180,
-							Direction::Left => Rotate::R270,
-						};
-						Some((snaek_sheet.snake_end, rotate))
-					}
-					(false, false) => None,
-				};
+				if let Some(banana) = slot.banana().filter(|_| !blinked_out) {
+					let banana_sprite = match banana {
+						Banana::Yellow => snaek_sheet.banana_yellow,
+						Banana::Red => snaek_sheet.banana_red,
+						Banana::Cyan => snaek_sheet.banana_cyan,
+					};
 
-				if let Some((snake_sprite, rotate)) = snake_sprite {
 					let sprite = ui.build_widget(
-						WidgetProps::simple_sprite(wk!(ikey_x, ikey_y), snaek_sheet_id, snake_sprite)
-							.with_rotate(rotate)
+						WidgetProps::simple_sprite(wk!(), snaek_sheet_id, banana_sprite)
 							.with_anchor_origin(Anchor::CENTER, Anchor::CENTER),
 					);
 					ui.add_child(sprite_holder.id(), sprite.id());
@@ -678,71 +1574,799 @@ fn snaek_playfield(
 		}
 	}
 
-	if snake_game.ate_banana() {
-		let head_pos = snake_game.snake_head();
+	// Where to draw what's in a slot that was entered from `direction` during the last tick. Both the head and the tail
+	// lag behind by the same amount, so the snake keeps its length. Wrapping around the playfield would have them
+	// slide in from outside of it, so they jump instead.
+	let lag = ((1.0 - tick_progress) * 7.0).round() as i16;
+	let slide = |slot_pos: Pos, direction: Option<Direction>| {
+		direction.filter(|&direction| {
+			let from = slot_pos - direction.pos_offset();
+			lag > 0 && (0..playfield_size.w as i16).contains(&from.x) && (0..playfield_size.h as i16).contains(&from.y)
+		})
+	};
+	let draw_pos = |slot_pos: Pos, direction: Option<Direction>| match slide(slot_pos, direction) {
+		Some(direction) => slot_pos * 7 - direction.pos_offset() * lag,
+		None => slot_pos * 7,
+	};
 
-		let (rotate, anchor) = match snake_game.slot_at(head_pos).direction_prev() {
-			Direction::Up => (Rotate::R90, Anchor::TOP_CENTER),
-			Direction::Right => (Rotate::R180, Anchor::CENTER_RIGHT),
-			Direction::Down => (Rotate::R270, Anchor::BOTTOM_CENTER),
-			Direction::Left => (Rotate::R0, Anchor::CENTER_LEFT),
-		};
+	for (snake_index, snake) in snake_game.snakes().iter().enumerate() {
+		let snake_len = snake.len() as usize;
+		let ikey_snake = snake_index as u64;
+		let (mask_and, mask_or) = SNAKE_TINTS[snake_index].unzip();
+		let (head_move, tail_move) = snake_game.last_move(snake_index);
+
+		for (i, mut segment) in snake_game.segments(snake_index).enumerate() {
+			let (is_tail, is_head) = (i == 0, i + 1 == snake_len);
+
+			if is_head && hide_dead_heads && snake.is_dead() {
+				continue;
+			}
 
-		let tongue_pos = head_pos + snake_game.direction().pos_offset();
-		let tongue_holder = ui.build_widget(
+			// A tail on its way in only covers part of its slot, so the slot gets the body piece it'll turn into once
+			// the tail moves on, and the tail goes on top of it.
+			let sliding_tail = slide(segment.pos, tail_move.filter(|_| is_tail));
+			if let Some(direction) = sliding_tail {
+				segment.direction_in = direction;
+			}
+
+			let (snake_sprite, rotate) = if is_head {
+				let rotate = match segment.direction_in {
+					Direction::Up => Rotate::R270,
+					Direction::Right => Rotate::R0,
+					Direction::Down => Rotate::R90,
+					Direction::Left => Rotate::R180,
+				};
+				(snaek_sheet.snake_head, rotate)
+			} else if is_tail && sliding_tail.is_none() {
+				let rotate = match segment.direction_out {
+					Direction::Up => Rotate::R0,
+					Direction::Right => Rotate::R90,
+					Direction::Down => Rotate::R180,
+					Direction::Left => Rotate::R270,
+				};
+				(snaek_sheet.snake_end, rotate)
+			} else if segment.direction_in == segment.direction_out {
+				let rotate = match segment.direction_out {
+					Direction::Up => Rotate::R270,
+					Direction::Right => Rotate::R0,
+					Direction::Down => Rotate::R90,
+					Direction::Left => Rotate::R180,
+				};
+				(snaek_sheet.snake_straight, rotate)
+			} else {
+				use Direction as D;
+				let rotate = match (segment.direction_out, segment.direction_in.opposite()) {
+					(D::Up, D::Right) | (D::Right, D::Up) => Rotate::R270,
+					(D::Right, D::Down) | (D::Down, D::Right) => Rotate::R0,
+					(D::Down, D::Left) | (D::Left, D::Down) => Rotate::R90,
+					(D::Left, D::Up) | (D::Up, D::Left) => Rotate::R180,
+					_ => Rotate::R0,
+				};
+				(snaek_sheet.snake_gay, rotate)
+			};
+
+			let (ikey_x, ikey_y) = (segment.pos.x as u64, segment.pos.y as u64);
+			let segment_holder = ui.build_widget(
+				WidgetProps::new(wk!(ikey_snake, ikey_x, ikey_y))
+					.with_size(WidgetSize::fixed(7, 7))
+					.with_pos(draw_pos(segment.pos, head_move.filter(|_| is_head))),
+			);
+			{
+				let sprite = ui.build_widget(
+					WidgetProps::simple_sprite(wk!(ikey_snake, ikey_x, ikey_y), snaek_sheet_id, snake_sprite)
+						.with_rotate(rotate)
+						.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+						.with_mask_and(mask_and)
+						.with_mask_or(mask_or),
+				);
+				ui.add_child(segment_holder.id(), sprite.id());
+			}
+			ui.add_child(container_id, segment_holder.id());
+
+			if let Some(direction) = sliding_tail {
+				let rotate = match direction {
+					Direction::Up => Rotate::R0,
+					Direction::Right => Rotate::R90,
+					Direction::Down => Rotate::R180,
+					Direction::Left => Rotate::R270,
+				};
+
+				let tail_holder = ui.build_widget(
+					WidgetProps::new(wk!(ikey_snake))
+						.with_size(WidgetSize::fixed(7, 7))
+						.with_pos(draw_pos(segment.pos, sliding_tail)),
+				);
+				{
+					let sprite = ui.build_widget(
+						WidgetProps::simple_sprite(wk!(ikey_snake), snaek_sheet_id, snaek_sheet.snake_end)
+							.with_rotate(rotate)
+							.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+							.with_mask_and(mask_and)
+							.with_mask_or(mask_or),
+					);
+					ui.add_child(tail_holder.id(), sprite.id());
+				}
+				ui.add_child(container_id, tail_holder.id());
+			}
+		}
+
+		if snake.ate_banana() {
+			let head_pos = snake.head();
+
+			let (rotate, anchor) = match snake_game.slot_at(head_pos).direction_prev() {
+				Direction::Up => (Rotate::R90, Anchor::TOP_CENTER),
+				Direction::Right => (Rotate::R180, Anchor::CENTER_RIGHT),
+				Direction::Down => (Rotate::R270, Anchor::BOTTOM_CENTER),
+				Direction::Left => (Rotate::R0, Anchor::CENTER_LEFT),
+			};
+
+			// the tongue sticks out of the head wherever it's drawn
+			let tongue_pos = draw_pos(head_pos, head_move) + snake.direction().pos_offset() * 7;
+			let tongue_holder = ui.build_widget(
+				WidgetProps::new(wk!(ikey_snake))
+					.with_size(WidgetSize::fixed(7, 7))
+					.with_pos(tongue_pos),
+			);
+			{
+				let tongue = ui.build_widget(
+					WidgetProps::simple_sprite(wk!(ikey_snake), snaek_sheet_id, snaek_sheet.snake_tongue)
+						.with_anchor_origin(anchor, anchor)
+						.with_rotate(rotate),
+				);
+				ui.add_child(tongue_holder.id(), tongue.id());
+			}
+			ui.add_child(container_id, tongue_holder.id());
+		}
+
+		// Mark where the snake crashed. When that's the solid border, the head right next to it gets marked instead.
+		if let Some(death_pos) = snake.death_pos() {
+			let death_pos = pos(
+				death_pos.x.clamp(0, playfield_size.w as i16 - 1),
+				death_pos.y.clamp(0, playfield_size.h as i16 - 1),
+			);
+
+			let crash_marker = ui.build_widget(
+				WidgetProps::new(wk!(ikey_snake))
+					.with_flags(WidgetFlags::DRAW_BORDER)
+					.with_border_color(Color::from_hex(0xffe43b44))
+					.with_border_width(1)
+					.with_size(WidgetSize::fixed(7, 7))
+					.with_pos(death_pos * 7),
+			);
+			ui.add_child(container_id, crash_marker.id());
+		}
+	}
+
+	if snake_game.is_won() {
+		let won_overlay = ui.build_widget(
 			WidgetProps::new(wk!())
-				.with_size(WidgetSize::fixed(7, 7))
-				.with_pos(tongue_pos * 7),
+				.with_flags(WidgetFlags::DRAW_BACKGROUND)
+				.with_color(Color::from_hex(0x4099e550))
+				.with_size(WidgetSize::fill()),
+		);
+		ui.add_child(container_id, won_overlay.id());
+	}
+}
+
+/// What the player picked on the pause overlay.
+enum PausedChoice {
+	NextMode,
+	NextDifficulty,
+}
+
+/// Overlay shown while the game is paused, with buttons to start over in the next mode or at the next difficulty.
+fn snaek_paused(
+	mode: Mode,
+	difficulty: Option<Difficulty>,
+	ui: &mut UiContext,
+	renderer: &Renderer,
+	container_id: WidgetId,
+	snaek_sheet_id: SpritesheetId,
+	snaek_sheet: &SnaekSheet,
+) -> Option<PausedChoice> {
+	let mut choice = None;
+
+	let paused_overlay = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_flags(WidgetFlags::DRAW_BACKGROUND)
+			.with_color(Color::from_hex(0x80ffffff & SNAEK_BLACK.to_u32()))
+			.with_size(WidgetSize::fill()),
+	);
+	{
+		let column = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::hug())
+				.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+				.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 4)),
 		);
 		{
-			let tongue = ui.build_widget(
-				WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.snake_tongue)
-					.with_anchor_origin(anchor, anchor)
-					.with_rotate(rotate),
+			let paused_text = ui.build_widget(
+				WidgetProps::text(wk!(), renderer.text("Paused"))
+					.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER),
 			);
-			ui.add_child(tongue_holder.id(), tongue.id());
+			ui.add_child(column.id(), paused_text.id());
+
+			let mode_text =
+				ui.build_widget(WidgetProps::text(wk!(), renderer.text(mode.name())).with_mask_and(Some(SNAEK_BLACK)));
+
+			let mode_btn = ui.btn_box(
+				WidgetProps::new(wk!())
+					.with_size(WidgetSize::hug())
+					.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
+					.with_padding(WidgetPadding::hv(4, 2)),
+				WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
+				WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
+				mode_text.id(),
+			);
+			ui.add_child(column.id(), mode_btn.id());
+
+			if mode_btn.clicked() {
+				choice = Some(PausedChoice::NextMode);
+			}
+
+			// rules that don't match any difficulty were picked some other way, like in a saved game
+			let difficulty_label = difficulty.map_or("Custom", Difficulty::name);
+			let difficulty_text = ui.build_widget(
+				WidgetProps::text(wk!(), renderer.text(difficulty_label)).with_mask_and(Some(SNAEK_BLACK)),
+			);
+
+			let difficulty_btn = ui.btn_box(
+				WidgetProps::new(wk!())
+					.with_size(WidgetSize::hug())
+					.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
+					.with_padding(WidgetPadding::hv(4, 2)),
+				WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
+				WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
+				difficulty_text.id(),
+			);
+			ui.add_child(column.id(), difficulty_btn.id());
+
+			if difficulty_btn.clicked() {
+				choice = Some(PausedChoice::NextDifficulty);
+			}
 		}
-		ui.add_child(container_id, tongue_holder.id());
+		ui.add_child(paused_overlay.id(), column.id());
 	}
+	ui.add_child(container_id, paused_overlay.id());
+
+	choice
+}
 
-	if *show_game_over {
-		let game_over_overlay = ui.build_widget(
+/// What the player picked on the game over overlay.
+enum GameOverChoice {
+	Dismiss,
+	SubmitName,
+	WatchReplay,
+	Rewind,
+}
+
+/// Overlay shown when a game ends. If the game made it in the high score table, it asks for a name to go with it.
+///
+/// In practice mode, the game can be rewound instead of watching its replay.
+#[allow(clippy::too_many_arguments)]
+fn snaek_game_over(
+	title: &'static str,
+	subtitle: Option<String>,
+	name_entry: Option<&str>,
+	can_rewind: bool,
+	ui: &mut UiContext,
+	renderer: &Renderer,
+	container_id: WidgetId,
+	snaek_sheet_id: SpritesheetId,
+	snaek_sheet: &SnaekSheet,
+) -> Option<GameOverChoice> {
+	let mut choice = None;
+
+	let game_over_overlay = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_flags(WidgetFlags::DRAW_BACKGROUND)
+			.with_color(Color::from_hex(0x80ffffff & SNAEK_BLACK.to_u32()))
+			.with_size(WidgetSize::fill()),
+	);
+	{
+		let column = ui.build_widget(
 			WidgetProps::new(wk!())
-				.with_flags(WidgetFlags::DRAW_BACKGROUND)
-				.with_color(Color::from_hex(0x80ffffff & SNAEK_BLACK.to_u32()))
-				.with_size(WidgetSize::fill()),
+				.with_size(WidgetSize::hug())
+				.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+				.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 4)),
 		);
 		{
-			let column = ui.build_widget(
+			let game_over_text = ui.build_widget(
+				WidgetProps::text(wk!(), renderer.text(title))
+					.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER),
+			);
+			ui.add_child(column.id(), game_over_text.id());
+
+			if let Some(subtitle) = subtitle {
+				let subtitle_text = ui.build_widget(
+					WidgetProps::text(wk!(), renderer.text(subtitle))
+						.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
+						.with_mask_and(Some(Color::from_hex(0xff8b9bb4))),
+				);
+				ui.add_child(column.id(), subtitle_text.id());
+			}
+
+			if let Some(name) = name_entry {
+				let name_box = ui.build_widget(
+					WidgetProps::nine_slice_sprite(wk!(), snaek_sheet_id, snaek_sheet.box_text_input)
+						.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
+						.with_size(WidgetSize::new(
+							WidgetDim::Fixed(MAX_NAME_LEN as u16 * 5 + 6),
+							WidgetDim::Hug,
+						))
+						.with_padding(WidgetPadding::hv(3, 2)),
+				);
+				{
+					// the cursor goes away once the name can't get any longer
+					let text = match name.chars().count() < MAX_NAME_LEN {
+						true => format!("{name}_"),
+						false => name.to_owned(),
+					};
+
+					let name_text =
+						ui.build_widget(WidgetProps::text(wk!(), renderer.text(text)).with_mask_and(Some(SNAEK_BLACK)));
+					ui.add_child(name_box.id(), name_text.id());
+				}
+				ui.add_child(column.id(), name_box.id());
+			}
+
+			let buttons = ui.build_widget(
 				WidgetProps::new(wk!())
 					.with_size(WidgetSize::hug())
-					.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
-					.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 4)),
+					.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
+					.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 3)),
 			);
 			{
-				let game_over_text = ui.build_widget(WidgetProps::text(wk!(), renderer.text("Game Over! :(")));
-				ui.add_child(column.id(), game_over_text.id());
-
+				let oh_label = if name_entry.is_some() { "OK" } else { "Oh" };
 				let oh_text =
-					ui.build_widget(WidgetProps::text(wk!(), renderer.text("Oh")).with_mask_and(Some(SNAEK_BLACK)));
+					ui.build_widget(WidgetProps::text(wk!(), renderer.text(oh_label)).with_mask_and(Some(SNAEK_BLACK)));
 
 				let oh_btn = ui.btn_box(
 					WidgetProps::new(wk!())
 						.with_size(WidgetSize::hug())
-						.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
 						.with_padding(WidgetPadding::hv(4, 2)),
 					WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
 					WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
 					oh_text.id(),
 				);
-				ui.add_child(column.id(), oh_btn.id());
+				ui.add_child(buttons.id(), oh_btn.id());
 
 				if oh_btn.clicked() {
-					*show_game_over = false;
+					choice = match name_entry {
+						Some(_) => Some(GameOverChoice::SubmitName),
+						None => Some(GameOverChoice::Dismiss),
+					};
+				}
+
+				let replay_label = if can_rewind { "Rewind" } else { "Replay" };
+				let replay_text = ui.build_widget(
+					WidgetProps::text(wk!(), renderer.text(replay_label)).with_mask_and(Some(SNAEK_BLACK)),
+				);
+
+				let replay_btn = ui.btn_box(
+					WidgetProps::new(wk!())
+						.with_size(WidgetSize::hug())
+						.with_padding(WidgetPadding::hv(4, 2)),
+					WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
+					WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
+					replay_text.id(),
+				);
+				ui.add_child(buttons.id(), replay_btn.id());
+
+				if replay_btn.clicked() {
+					choice = match can_rewind {
+						true => Some(GameOverChoice::Rewind),
+						false => Some(GameOverChoice::WatchReplay),
+					};
 				}
 			}
-			ui.add_child(game_over_overlay.id(), column.id());
+			ui.add_child(column.id(), buttons.id());
 		}
-		ui.add_child(container_id, game_over_overlay.id());
+		ui.add_child(game_over_overlay.id(), column.id());
 	}
+	ui.add_child(container_id, game_over_overlay.id());
+
+	choice
+}
+
+/// The high score table, with the details of the hovered score at the bottom. Returns whether it should be closed.
+#[allow(clippy::too_many_arguments)]
+fn snaek_high_scores(
+	high_scores: &HighScores,
+	new_high_score: Option<usize>,
+	ui: &mut UiContext,
+	renderer: &Renderer,
+	container_id: WidgetId,
+	snaek_sheet_id: SpritesheetId,
+	snaek_sheet: &SnaekSheet,
+) -> bool {
+	let highlight_color = Color::from_hex(0xfffee761);
+
+	let scores_overlay = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_flags(WidgetFlags::DRAW_BACKGROUND)
+			.with_color(Color::from_hex(0xc0ffffff & SNAEK_BLACK.to_u32()))
+			.with_size(WidgetSize::fill())
+			.with_padding(WidgetPadding::all(3))
+			.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
+	);
+
+	let header = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug))
+			.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 0)),
+	);
+	let btn_close = {
+		let title_holder =
+			ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug)));
+		{
+			let title = ui.build_widget(WidgetProps::text(wk!(), renderer.text("High scores")));
+			ui.add_child(title_holder.id(), title.id());
+		}
+		ui.add_child(header.id(), title_holder.id());
+
+		let btn_close = ui.btn_icon(
+			WidgetProps::new(wk!()).with_size(WidgetSize::fixed(3, 3)),
+			WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.icon_close)
+				.with_mask_and(Some(Color::from_hex(0xffc0cbdc))),
+			Color::from_hex(0xffe43b44),
+		);
+		ui.add_child(header.id(), btn_close.id());
+
+		btn_close
+	};
+	ui.add_child(scores_overlay.id(), header.id());
+
+	let list = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug))
+			.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 1)),
+	);
+	let mut shown_entry = new_high_score;
+	{
+		if high_scores.entries().is_empty() {
+			let empty_text = ui.build_widget(
+				WidgetProps::text(wk!(), renderer.text("No scores yet"))
+					.with_mask_and(Some(Color::from_hex(0xff8b9bb4))),
+			);
+			ui.add_child(list.id(), empty_text.id());
+		}
+
+		for (rank, entry) in high_scores.entries().iter().enumerate() {
+			let ikey_rank = rank as u64;
+			let mask_and = (Some(rank) == new_high_score).then_some(highlight_color);
+
+			let row = ui.build_widget(
+				WidgetProps::new(wk!(ikey_rank))
+					.with_flags(WidgetFlags::CAN_HOVER)
+					.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug))
+					.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 0)),
+			);
+			{
+				let columns = [
+					(WidgetDim::Fixed(6), Anchor::TOP_LEFT, (rank + 1).to_string()),
+					(
+						WidgetDim::Fixed(MAX_NAME_LEN as u16 * 5),
+						Anchor::TOP_LEFT,
+						entry.name.clone(),
+					),
+					(WidgetDim::Fill, Anchor::TOP_RIGHT, entry.score.to_string()),
+				];
+
+				for (i, (width, anchor, text)) in columns.into_iter().enumerate() {
+					let ikey_column = i as u64;
+
+					let cell = ui.build_widget(
+						WidgetProps::new(wk!(ikey_rank, ikey_column)).with_size(WidgetSize::new(width, WidgetDim::Hug)),
+					);
+					{
+						let text = ui.build_widget(
+							WidgetProps::text(wk!(ikey_rank, ikey_column), renderer.text(text))
+								.with_anchor_origin(anchor, anchor)
+								.with_mask_and(mask_and),
+						);
+						ui.add_child(cell.id(), text.id());
+					}
+					ui.add_child(row.id(), cell.id());
+				}
+			}
+			ui.add_child(list.id(), row.id());
+
+			if row.hovered() {
+				shown_entry = Some(rank);
+			}
+		}
+	}
+	ui.add_child(scores_overlay.id(), list.id());
+
+	if let Some(entry) = shown_entry.and_then(|rank| high_scores.entries().get(rank)) {
+		let secs = entry.duration.as_secs();
+		let details = [
+			entry.date.to_string(),
+			format!("{:02}:{:02} {}", secs / 60, secs % 60, entry.level),
+			format!("{} {}", entry.difficulty.name(), entry.border.name()),
+		];
+
+		let details_column = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::hug())
+				.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 1)),
+		);
+		for (i, line) in details.into_iter().enumerate() {
+			let ikey_line = i as u64;
+
+			let text = ui.build_widget(
+				WidgetProps::text(wk!(ikey_line), renderer.text(line)).with_mask_and(Some(Color::from_hex(0xff8b9bb4))),
+			);
+			ui.add_child(details_column.id(), text.id());
+		}
+		ui.add_child(scores_overlay.id(), details_column.id());
+	}
+
+	ui.add_child(container_id, scores_overlay.id());
+
+	btn_close.clicked()
+}
+
+/// Lifetime stats and achievements, with the details of the hovered achievement at the bottom. Returns whether it
+/// should be closed.
+#[allow(clippy::too_many_arguments)]
+fn snaek_stats(
+	stats: &Stats,
+	new_achievements: &[Achievement],
+	ui: &mut UiContext,
+	renderer: &Renderer,
+	container_id: WidgetId,
+	snaek_sheet_id: SpritesheetId,
+	snaek_sheet: &SnaekSheet,
+) -> bool {
+	let highlight_color = Color::from_hex(0xfffee761);
+	let dim_color = Color::from_hex(0xff8b9bb4);
+
+	let stats_overlay = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_flags(WidgetFlags::DRAW_BACKGROUND)
+			.with_color(Color::from_hex(0xc0ffffff & SNAEK_BLACK.to_u32()))
+			.with_size(WidgetSize::fill())
+			.with_padding(WidgetPadding::all(3))
+			.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
+	);
+
+	let header = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug))
+			.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 0)),
+	);
+	let btn_close = {
+		let title_holder =
+			ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug)));
+		{
+			let title = ui.build_widget(WidgetProps::text(wk!(), renderer.text("Stats")));
+			ui.add_child(title_holder.id(), title.id());
+		}
+		ui.add_child(header.id(), title_holder.id());
+
+		let btn_close = ui.btn_icon(
+			WidgetProps::new(wk!()).with_size(WidgetSize::fixed(3, 3)),
+			WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.icon_close)
+				.with_mask_and(Some(Color::from_hex(0xffc0cbdc))),
+			Color::from_hex(0xffe43b44),
+		);
+		ui.add_child(header.id(), btn_close.id());
+
+		btn_close
+	};
+	ui.add_child(stats_overlay.id(), header.id());
+
+	let list = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug))
+			.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 1)),
+	);
+	{
+		let secs = stats.play_time.as_secs();
+		let rows = [
+			("Games", stats.games_played.to_string()),
+			(
+				"Play time",
+				format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+			),
+			("Longest", stats.longest_snake.to_string()),
+			("Fastest", format!("{:.1}/s", stats.fastest_speed)),
+		];
+
+		for (i, (label, value)) in rows.into_iter().enumerate() {
+			let ikey_row = i as u64;
+
+			let row = ui.build_widget(
+				WidgetProps::new(wk!(ikey_row))
+					.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug))
+					.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 0)),
+			);
+			{
+				let label_text = ui.build_widget(
+					WidgetProps::text(wk!(ikey_row), renderer.text(label)).with_mask_and(Some(dim_color)),
+				);
+				ui.add_child(row.id(), label_text.id());
+
+				let value_cell = ui.build_widget(
+					WidgetProps::new(wk!(ikey_row)).with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Hug)),
+				);
+				{
+					let value_text = ui.build_widget(
+						WidgetProps::text(wk!(ikey_row), renderer.text(value))
+							.with_anchor_origin(Anchor::TOP_RIGHT, Anchor::TOP_RIGHT),
+					);
+					ui.add_child(value_cell.id(), value_text.id());
+				}
+				ui.add_child(row.id(), value_cell.id());
+			}
+			ui.add_child(list.id(), row.id());
+		}
+
+		let bananas_row = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::hug())
+				.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 2)),
+		);
+		{
+			let bananas = [
+				(snaek_sheet.banana_yellow, stats.yellow_bananas),
+				(snaek_sheet.banana_red, stats.red_bananas),
+				(snaek_sheet.banana_cyan, stats.cyan_bananas),
+			];
+
+			for (i, (sprite, count)) in bananas.into_iter().enumerate() {
+				let ikey_banana = i as u64;
+
+				let counter = ui.build_widget(
+					WidgetProps::new(wk!(ikey_banana))
+						.with_size(WidgetSize::hug())
+						.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 1)),
+				);
+				{
+					let icon = ui.build_widget(WidgetProps::simple_sprite(wk!(ikey_banana), snaek_sheet_id, sprite));
+					ui.add_child(counter.id(), icon.id());
+
+					let text = ui.build_widget(
+						WidgetProps::text(wk!(ikey_banana), renderer.text(count.to_string()))
+							.with_anchor_origin(Anchor::BOTTOM_LEFT, Anchor::BOTTOM_LEFT),
+					);
+					ui.add_child(counter.id(), text.id());
+				}
+				ui.add_child(bananas_row.id(), counter.id());
+			}
+		}
+		ui.add_child(list.id(), bananas_row.id());
+	}
+	ui.add_child(stats_overlay.id(), list.id());
+
+	let achievements_row = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_size(WidgetSize::hug())
+			.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 2)),
+	);
+	let mut hovered_achievement = None;
+	{
+		for (i, achievement) in Achievement::ALL.into_iter().enumerate() {
+			let ikey_achievement = i as u64;
+
+			let color = match stats.has(achievement) {
+				true => highlight_color,
+				false => Color::from_hex(0xff3a4466),
+			};
+
+			// the ones the current game unlocked get a frame around them
+			let mut props = WidgetProps::new(wk!(ikey_achievement))
+				.with_flags(WidgetFlags::CAN_HOVER | WidgetFlags::DRAW_BACKGROUND)
+				.with_color(color)
+				.with_size(WidgetSize::fixed(5, 5));
+			if new_achievements.contains(&achievement) {
+				props = props
+					.with_flags(WidgetFlags::CAN_HOVER | WidgetFlags::DRAW_BACKGROUND | WidgetFlags::DRAW_BORDER)
+					.with_border_color(Color::from_hex(0xffffffff))
+					.with_border_width(1);
+			}
+
+			let tile = ui.build_widget(props);
+			ui.add_child(achievements_row.id(), tile.id());
+
+			if tile.hovered() {
+				hovered_achievement = Some(achievement);
+			}
+		}
+	}
+	ui.add_child(stats_overlay.id(), achievements_row.id());
+
+	let details = match hovered_achievement {
+		Some(achievement) => vec![achievement.name().to_owned(), achievement.description().to_owned()],
+		None => {
+			let unlocked = Achievement::ALL
+				.iter()
+				.filter(|&&achievement| stats.has(achievement))
+				.count();
+			vec![format!("{}/{} unlocked", unlocked, Achievement::ALL.len())]
+		}
+	};
+
+	let details_column = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_size(WidgetSize::hug())
+			.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 1)),
+	);
+	for (i, line) in details.into_iter().enumerate() {
+		let ikey_line = i as u64;
+
+		let text =
+			ui.build_widget(WidgetProps::text(wk!(ikey_line), renderer.text(line)).with_mask_and(Some(dim_color)));
+		ui.add_child(details_column.id(), text.id());
+	}
+	ui.add_child(stats_overlay.id(), details_column.id());
+
+	ui.add_child(container_id, stats_overlay.id());
+
+	btn_close.clicked()
+}
+
+/// Seek bar and exit button of the replay viewer. Returns whether the viewer should be closed.
+#[allow(clippy::too_many_arguments)]
+fn snaek_replay_controls(
+	replay_player: &mut ReplayPlayer,
+	ui: &mut UiContext,
+	mouse: &Mouse,
+	container_id: WidgetId,
+	container_width: u16,
+	snaek_sheet_id: SpritesheetId,
+	snaek_sheet: &SnaekSheet,
+) -> bool {
+	let controls = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_flags(WidgetFlags::DRAW_BACKGROUND)
+			.with_color(Color::from_hex(0x80ffffff & SNAEK_BLACK.to_u32()))
+			.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Fixed(7)))
+			.with_anchor_origin(Anchor::BOTTOM_LEFT, Anchor::BOTTOM_LEFT)
+			.with_padding(WidgetPadding::all(2))
+			.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 2)),
+	);
+
+	// padding on both sides, the gap and the exit button
+	let bar_width = container_width.saturating_sub(2 + 2 + 2 + 3);
+
+	let seek_bar = ui.build_widget(
+		WidgetProps::new(wk!())
+			.with_flags(WidgetFlags::CAN_HOVER | WidgetFlags::CAN_CLICK | WidgetFlags::DRAW_BACKGROUND)
+			.with_color(SNAEK_BLACK)
+			.with_size(WidgetSize::fixed(bar_width, 3)),
+	);
+	{
+		let total_ticks = replay_player.replay().ticks.max(1);
+		let progress_width = (bar_width as u64 * replay_player.game().ticks().min(total_ticks) / total_ticks) as u16;
+
+		let progress = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_flags(WidgetFlags::DRAW_BACKGROUND)
+				.with_color(Color::from_hex(0xff99e550))
+				.with_size(WidgetSize::fixed(progress_width, 3)),
+		);
+		ui.add_child(seek_bar.id(), progress.id());
+
+		if seek_bar.pressed() {
+			let rect = ui.widget(seek_bar.id()).solved_rect();
+			let ratio = ((mouse.x - rect.x as f64) / rect.w.max(1) as f64).clamp(0.0, 1.0);
+			replay_player.seek((ratio * total_ticks as f64).round() as u64);
+		}
+	}
+	ui.add_child(controls.id(), seek_bar.id());
+
+	let btn_exit = ui.btn_icon(
+		WidgetProps::new(wk!()).with_size(WidgetSize::fixed(3, 3)),
+		WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.icon_close)
+			.with_mask_and(Some(Color::from_hex(0xffc0cbdc))),
+		Color::from_hex(0xffe43b44),
+	);
+	ui.add_child(controls.id(), btn_exit.id());
+
+	ui.add_child(container_id, controls.id());
+
+	btn_exit.clicked()
 }
@@ -154,7 +154,7 @@ pub enum Rotate {
 	R270,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum DrawCommand {
 	Clear,
 	Fill {
@@ -0,0 +1,297 @@
+//! Headless batch runs of the game, for balancing.
+//!
+//! `snaek sim` plays a whole range of seeds with bots at full speed, without opening a window, and prints what came out
+//! of it. Each combination of bot and rules preset is a session of its own, which makes it easy to compare them.
+
+use std::error::Error;
+use std::ops::Range;
+use std::path::Path;
+
+use owo_colors::OwoColorize;
+
+use crate::snake::{bot_by_name, builtin_levels, Border, DeathCause, Level, Mode, Rules, SnakeGame, BOT_NAMES};
+use crate::LEVEL_EXTENSION;
+
+const USAGE: &str = "\
+Usage: snaek sim [options]
+
+Options:
+  --seeds <start>..<end>   Seeds to play, one game each (default: 0..1000)
+  --bot <names>            Bots to play with, separated by commas (default: greedy)
+  --rules <presets>        Rules presets to play with, separated by commas (default: wrap)
+  --level <name or path>   Built-in level name or .snaeklevel file (default: Classic)
+  --mode <name>            Game mode: classic, zen, time-attack or banana-goal (default: classic)
+  --bananas <n>            Bananas on the playfield at the same time (default: 1)
+  --max-ticks <n>          Ticks after which a game is stopped (default: 100000)
+  --csv                    Print the results as CSV instead of a table";
+
+/// Names of the rules presets a session can be played with.
+const RULES_PRESETS: [&str; 2] = ["wrap", "solid"];
+
+fn rules_preset(name: &str) -> Option<Rules> {
+	let border = match name {
+		"wrap" => Border::Wrap,
+		"solid" => Border::Solid,
+		_ => return None,
+	};
+
+	Some(Rules {
+		border,
+		..Rules::default()
+	})
+}
+
+struct SimOptions {
+	seeds: Range<u64>,
+	bots: Vec<String>,
+	rules: Vec<String>,
+	level: Level,
+	mode: Mode,
+	bananas: u8,
+	max_ticks: u64,
+	csv: bool,
+}
+
+impl SimOptions {
+	fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+		let mut options = Self {
+			seeds: 0..1000,
+			bots: vec!["greedy".to_owned()],
+			rules: vec!["wrap".to_owned()],
+			level: builtin_levels().swap_remove(0),
+			mode: Mode::Classic,
+			bananas: 1,
+			max_ticks: 100_000,
+			csv: false,
+		};
+
+		while let Some(arg) = args.next() {
+			let mut value = || args.next().ok_or_else(|| format!("missing value after {arg}"));
+
+			match arg.as_str() {
+				"--seeds" => {
+					let value = value()?;
+					let (start, end) = value.split_once("..").ok_or("seeds should look like 0..1000")?;
+					options.seeds = start.parse()?..end.parse()?;
+				}
+				"--bot" => options.bots = value()?.split(',').map(str::to_owned).collect(),
+				"--rules" => options.rules = value()?.split(',').map(str::to_owned).collect(),
+				"--level" => options.level = find_level(&value()?)?,
+				"--mode" => options.mode = find_mode(&value()?)?,
+				"--bananas" => options.bananas = value()?.parse()?,
+				"--max-ticks" => options.max_ticks = value()?.parse()?,
+				"--csv" => options.csv = true,
+				_ => return Err(format!("unknown option {arg}").into()),
+			}
+		}
+
+		if options.bananas == 0 {
+			return Err("there should be at least one banana".into());
+		}
+
+		if let Some(bot) = options.bots.iter().find(|bot| bot_by_name(bot).is_none()) {
+			return Err(format!("unknown bot {bot:?}, try one of {BOT_NAMES:?}").into());
+		}
+
+		if let Some(preset) = options.rules.iter().find(|preset| rules_preset(preset).is_none()) {
+			return Err(format!("unknown rules preset {preset:?}, try one of {RULES_PRESETS:?}").into());
+		}
+
+		Ok(options)
+	}
+}
+
+fn find_level(name: &str) -> Result<Level, Box<dyn Error>> {
+	let path = Path::new(name);
+	if path.extension().is_some_and(|ext| ext == LEVEL_EXTENSION) {
+		return Level::load(path);
+	}
+
+	(builtin_levels().into_iter())
+		.find(|level| level.name.eq_ignore_ascii_case(name))
+		.ok_or_else(|| format!("no built-in level is called {name:?}").into())
+}
+
+fn find_mode(name: &str) -> Result<Mode, Box<dyn Error>> {
+	(Mode::ALL.into_iter())
+		.find(|mode| mode.name().replace(' ', "-").eq_ignore_ascii_case(name))
+		.ok_or_else(|| format!("no game mode is called {name:?}").into())
+}
+
+/// Aggregated outcome of all the games of a session.
+#[derive(Debug, Default)]
+struct SessionStats {
+	games: u64,
+	total_score: u64,
+	max_score: u32,
+	total_ticks: u64,
+	total_bananas: u64,
+	won: u64,
+	self_collisions: u64,
+	wall_crashes: u64,
+	other_snake_crashes: u64,
+	/// Games that hit the tick limit before ending.
+	timeouts: u64,
+}
+
+impl SessionStats {
+	fn add(&mut self, game: &SnakeGame) {
+		self.games += 1;
+		self.total_ticks += game.ticks();
+
+		for snake in game.snakes() {
+			self.total_score += snake.score() as u64;
+			self.max_score = self.max_score.max(snake.score());
+			self.total_bananas += snake.bananas_eaten() as u64;
+
+			match snake.death_cause() {
+				Some(DeathCause::SelfCollision) => self.self_collisions += 1,
+				Some(DeathCause::Wall) => self.wall_crashes += 1,
+				Some(DeathCause::OtherSnake) => self.other_snake_crashes += 1,
+				None => {}
+			}
+		}
+
+		if game.is_won() {
+			self.won += 1;
+		} else if !game.is_over() {
+			self.timeouts += 1;
+		}
+	}
+
+	fn mean(&self, total: u64) -> f64 {
+		total as f64 / self.games.max(1) as f64
+	}
+}
+
+/// Plays a single game to the end, with a fresh bot for each snake.
+fn play(level: &Level, rules: &Rules, bot: &str, seed: u64, max_ticks: u64) -> SnakeGame {
+	let mut game = SnakeGame::new(level.clone(), rules.clone(), seed);
+	let mut bots: Vec<_> = (0..game.snakes().len()).filter_map(|_| bot_by_name(bot)).collect();
+
+	while !game.is_over() && game.ticks() < max_ticks {
+		for (snake, bot) in bots.iter_mut().enumerate() {
+			let direction = bot.next_direction(&game, snake);
+			game.change_direction(snake, direction);
+		}
+
+		game.update();
+	}
+
+	game
+}
+
+/// Entry point of `snaek sim`, with the arguments that come after it.
+pub fn run(args: impl Iterator<Item = String>) -> i32 {
+	let args: Vec<String> = args.collect();
+	if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+		println!("{USAGE}");
+		return 0;
+	}
+
+	let options = match SimOptions::parse(args.into_iter()) {
+		Ok(options) => options,
+		Err(e) => {
+			eprintln!("{}", "Couldn't start the simulation :(".red());
+			eprintln!("-> {}", e);
+			eprintln!();
+			eprintln!("{USAGE}");
+			return 1;
+		}
+	};
+
+	let mut sessions = Vec::new();
+	for bot in &options.bots {
+		for preset in &options.rules {
+			let rules = Rules {
+				mode: options.mode,
+				bananas: options.bananas,
+				..rules_preset(preset).unwrap_or_default()
+			};
+
+			let mut stats = SessionStats::default();
+			for seed in options.seeds.clone() {
+				stats.add(&play(&options.level, &rules, bot, seed, options.max_ticks));
+			}
+
+			sessions.push((bot.as_str(), preset.as_str(), stats));
+		}
+	}
+
+	match options.csv {
+		true => print_csv(&options.level, options.mode, &sessions),
+		false => print_table(&options.level, options.mode, &sessions),
+	}
+
+	0
+}
+
+fn print_table(level: &Level, mode: Mode, sessions: &[(&str, &str, SessionStats)]) {
+	println!(
+		"{} games per session",
+		sessions.first().map_or(0, |(_, _, stats)| stats.games)
+	);
+	println!();
+	println!(
+		"{:<10} {:<11} {:<8} {:<6} {:>10} {:>9} {:>10} {:>12} {:>6} {:>6} {:>6} {:>6} {:>8}",
+		"level",
+		"mode",
+		"bot",
+		"rules",
+		"mean score",
+		"max score",
+		"mean ticks",
+		"mean bananas",
+		"won",
+		"self",
+		"wall",
+		"other",
+		"timeout",
+	);
+
+	for (bot, preset, stats) in sessions {
+		println!(
+			"{:<10} {:<11} {:<8} {:<6} {:>10.2} {:>9} {:>10.1} {:>12.2} {:>6} {:>6} {:>6} {:>6} {:>8}",
+			level.name,
+			mode.name(),
+			bot,
+			preset,
+			stats.mean(stats.total_score),
+			stats.max_score,
+			stats.mean(stats.total_ticks),
+			stats.mean(stats.total_bananas),
+			stats.won,
+			stats.self_collisions,
+			stats.wall_crashes,
+			stats.other_snake_crashes,
+			stats.timeouts,
+		);
+	}
+}
+
+fn print_csv(level: &Level, mode: Mode, sessions: &[(&str, &str, SessionStats)]) {
+	println!(
+		"level,mode,bot,rules,games,mean_score,max_score,mean_ticks,mean_bananas,\
+		won,self_collisions,wall_crashes,other_snake_crashes,timeouts"
+	);
+
+	for (bot, preset, stats) in sessions {
+		println!(
+			"{},{},{},{},{},{:.4},{},{:.4},{:.4},{},{},{},{},{}",
+			level.name,
+			mode.name(),
+			bot,
+			preset,
+			stats.games,
+			stats.mean(stats.total_score),
+			stats.max_score,
+			stats.mean(stats.total_ticks),
+			stats.mean(stats.total_bananas),
+			stats.won,
+			stats.self_collisions,
+			stats.wall_crashes,
+			stats.other_snake_crashes,
+			stats.timeouts,
+		);
+	}
+}
@@ -1,19 +1,50 @@
+pub mod bot;
+pub mod clock;
+pub mod codec;
+pub mod daily;
+pub mod level;
+pub mod mode;
+pub mod replay;
+pub mod rewind;
+pub mod rng;
+pub mod rules;
+pub mod save;
+pub mod scores;
 pub mod snaeksheet;
-
-use std::time::{Duration, Instant};
-
-use rand::rngs::ThreadRng;
-use rand::Rng;
+pub mod stats;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub use bot::{bot_by_name, SnakeController, BOT_NAMES};
+pub use clock::{Clock, WallClock};
+// the game only runs on the wall clock, but anything else that tells the time can drive it too
+#[allow(unused_imports)]
+pub use clock::TimeSource;
+pub use daily::DailyHistory;
+pub use level::{builtin_levels, Level};
+pub use mode::Mode;
+use mode::{Collision, Ending};
+use rand::{Rng, SeedableRng};
+pub use replay::{Replay, ReplayInput, ReplayPlayer};
+pub use rewind::RewindBuffer;
+pub use rng::SnaekRng;
+pub use rules::{Border, Difficulty, Rules};
+pub use scores::{HighScore, HighScores, MAX_NAME_LEN};
 pub use snaeksheet::{snaek_sheet, SnaekSheet};
+pub use stats::{Achievement, Stats};
 
 use crate::math::pos::{pos, Pos};
 use crate::math::size::Size;
 
-fn rand_pos(rng: &mut ThreadRng, size: Size) -> Pos {
-	let x = rng.gen_range(0..size.w as i16);
-	let y = rng.gen_range(0..size.h as i16);
-	pos(x, y)
-}
+/// Maximum number of direction changes that can be buffered ahead of a snake.
+const INPUT_QUEUE_LEN: usize = 3;
+
+/// Maximum number of snakes on a playfield.
+pub const MAX_SNAKES: usize = 2;
+
+/// Marks a slot that isn't in the list of free slots.
+const NOT_FREE: u16 = u16::MAX;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
@@ -24,6 +55,8 @@ pub enum Direction {
 }
 
 impl Direction {
+	pub const ALL: [Self; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
 	pub const fn pos_offset(&self) -> Pos {
 		match self {
 			Direction::Up => pos(0, -1),
@@ -41,6 +74,34 @@ impl Direction {
 			Direction::Left => Direction::Right,
 		}
 	}
+
+	/// Inverse of `direction as u8`. Only the 2 lowest bits are taken into account.
+	pub const fn from_bits(bits: u8) -> Self {
+		match bits & 0b11 {
+			0 => Direction::Up,
+			1 => Direction::Right,
+			2 => Direction::Down,
+			_ => Direction::Left,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+	/// The snake bit itself.
+	SelfCollision,
+	/// The snake crashed into a wall or the solid border of the playfield.
+	Wall,
+	/// The snake crashed into another snake.
+	OtherSnake,
+}
+
+/// What the ground of a slot is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tile {
+	#[default]
+	Floor = 0,
+	Wall = 1,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -50,216 +111,897 @@ pub enum Banana {
 	Cyan = 3,
 }
 
-pub struct SnakeGame {
-	thread_rng: ThreadRng,
-	size: Size,
-	playfield: Box<[Slot]>,
-	snake_head: Pos,
-	snake_tail: Pos,
-	bananas_eaten: u32,
-	speed: f32,
+/// A banana lying on the playfield, as listed by [`SnakeGame::bananas`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlacedBanana {
+	pub kind: Banana,
+	pub pos: Pos,
+	/// Tick at which the banana goes away if nobody ate it by then.
+	pub despawn_tick: Option<u64>,
+}
+
+/// Something that happened during a tick, as listed by [`SnakeGame::events`].
+///
+/// Snakes are referred to by their index in [`SnakeGame::snakes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEvent {
+	/// A snake's head went to a new slot.
+	Moved {
+		snake: usize,
+		pos: Pos,
+		direction: Direction,
+	},
+	/// A snake ate a banana at the slot its head went to.
+	AteBanana { snake: usize, kind: Banana, pos: Pos },
+	/// A snake's tail stayed in place, making it one slot longer.
+	Grew { snake: usize, len: u32 },
+	/// A snake crashed into what was at `pos`, which is outside the playfield if it crashed into the solid border.
+	Died { snake: usize, cause: DeathCause, pos: Pos },
+	/// A snake ran into what was at `pos`, but the mode doesn't let that kill it. It stays where it was instead.
+	Blocked { snake: usize, cause: DeathCause, pos: Pos },
+	/// A new banana appeared on the playfield.
+	BananaSpawned { kind: Banana, pos: Pos },
+	/// A banana went away because nobody ate it in time.
+	BananaDespawned { kind: Banana, pos: Pos },
+	/// The snakes filled the whole playfield, or reached the goal of the mode.
+	Won,
+	/// The mode's time limit ran out.
+	TimeUp,
+}
+
+/// One of the snakes on the playfield.
+#[derive(Debug, Clone)]
+pub struct Snake {
+	head: Pos,
+	tail: Pos,
+	len: u32,
 	direction: Direction,
+	input_queue: VecDeque<Direction>,
+	score: u32,
+	bananas_eaten: u32,
+	/// Number of upcoming ticks where the tail stays in place.
+	pending_growth: u32,
 	ate_banana: bool,
-	is_dead: bool,
+	death_cause: Option<DeathCause>,
+	/// Slot the snake crashed into, if it's dead.
+	death_pos: Option<Pos>,
+}
+
+impl Snake {
+	fn new(head: Pos, tail: Pos, direction: Direction) -> Self {
+		Self {
+			head,
+			tail,
+			len: 2,
+			direction,
+			input_queue: VecDeque::with_capacity(INPUT_QUEUE_LEN),
+			score: 0,
+			bananas_eaten: 0,
+			pending_growth: 0,
+			ate_banana: false,
+			death_cause: None,
+			death_pos: None,
+		}
+	}
+
+	pub fn head(&self) -> Pos {
+		self.head
+	}
+
+	pub fn tail(&self) -> Pos {
+		self.tail
+	}
+
+	/// Number of slots the snake takes up.
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> u32 {
+		self.len
+	}
 
-	start: Instant,
+	pub fn direction(&self) -> Direction {
+		self.direction
+	}
+
+	pub fn score(&self) -> u32 {
+		self.score
+	}
+
+	pub fn bananas_eaten(&self) -> u32 {
+		self.bananas_eaten
+	}
+
+	pub fn ate_banana(&self) -> bool {
+		self.ate_banana
+	}
+
+	pub fn is_dead(&self) -> bool {
+		self.death_cause.is_some()
+	}
+
+	/// Why the snake died, if it's dead.
+	pub fn death_cause(&self) -> Option<DeathCause> {
+		self.death_cause
+	}
+
+	/// Slot the snake crashed into, if it's dead. It's just outside the playfield if it crashed into the solid border.
+	pub fn death_pos(&self) -> Option<Pos> {
+		self.death_pos
+	}
+}
+
+/// Where a snake is about to go during a tick.
+#[derive(Clone, Copy)]
+struct Move {
+	next_head: Pos,
+	banana: Option<Banana>,
+	growing: bool,
+}
+
+pub struct SnakeGame<R = SnaekRng> {
+	rng: R,
+	seed: Option<u64>,
+	rules: Rules,
+	level: Level,
+	size: Size,
+	playfield: Box<[Slot]>,
+	/// Indices of the slots a banana can be placed on, in no particular order.
+	free_slots: Vec<u16>,
+	/// Position of each slot in `free_slots`, or `NOT_FREE`.
+	free_slot_indices: Box<[u16]>,
+	snakes: Vec<Snake>,
+	/// Bananas on the playfield, oldest first.
+	bananas: Vec<PlacedBanana>,
+	/// Ticks at which new bananas are due, soonest first.
+	banana_spawns: VecDeque<u64>,
+	speed: f32,
+	/// Speed changes of the bananas eaten since the speed last changed, for [stepped](rules::SpeedCurve::Stepped) speeds.
+	pending_speed_change: f32,
+	won: bool,
+	inputs: Vec<ReplayInput>,
+	/// What happened during the last tick.
+	events: Vec<GameEvent>,
+	/// Number of times the game got taken back in time.
+	rewinds: u32,
+	/// Number of ticks the game had run for before it last got taken back in time.
+	rewound_from: u64,
+	/// Whether a bot steered any of the snakes at some point.
+	assisted: bool,
+
+	paused: bool,
+	ticks: u64,
+	tick_time: Duration,
 	duration: Duration,
 }
 
-impl SnakeGame {
-	pub fn new(playfield_size: Size) -> Self {
-		let thread_rng = rand::thread_rng();
+impl<R: Rng + SeedableRng> SnakeGame<R> {
+	/// Creates a new game where every banana placement and banana type roll is determined by `seed`.
+	pub fn new(level: Level, rules: Rules, seed: u64) -> Self {
+		let mut game = Self::with_rng(level, rules, R::seed_from_u64(seed));
+		game.seed = Some(seed);
+		game
+	}
+}
 
-		let playfield = vec![Slot::default(); playfield_size.w as usize * playfield_size.h as usize].into_boxed_slice();
-		let snake_head = pos((playfield_size.w / 2) as i16, (playfield_size.h / 2) as i16);
-		let snake_tail = snake_head - pos(-1, 0);
+impl<R: Rng> SnakeGame<R> {
+	/// # Panics
+	///
+	/// Panics if the level doesn't have a start for each of the snakes in the rules.
+	pub fn with_rng(level: Level, rules: Rules, rng: R) -> Self {
+		assert!(
+			rules.snakes as usize <= level.starts.len(),
+			"level {:?} only has room for {} snakes",
+			level.name,
+			level.starts.len(),
+		);
+
+		let playfield_size = level.size;
+		let slot_count = playfield_size.w as usize * playfield_size.h as usize;
+		let playfield = vec![Slot::default(); slot_count].into_boxed_slice();
 
 		let mut game = Self {
-			thread_rng,
+			rng,
+			seed: None,
+			speed: rules.start_speed,
+			pending_speed_change: 0.0,
+			snakes: Vec::with_capacity(rules.snakes as usize),
+			bananas: Vec::with_capacity(rules.bananas as usize),
+			banana_spawns: VecDeque::new(),
+			rules,
+			level,
 			size: playfield_size,
 			playfield,
-			snake_head,
-			snake_tail,
-			bananas_eaten: 0,
-			direction: Direction::Right,
-			ate_banana: false,
-			is_dead: false,
-			speed: 3.0,
-
-			start: Instant::now(),
-			duration: Duration::default(),
+			free_slots: Vec::with_capacity(slot_count),
+			free_slot_indices: vec![NOT_FREE; slot_count].into_boxed_slice(),
+			won: false,
+			inputs: Vec::new(),
+			events: Vec::new(),
+			rewinds: 0,
+			rewound_from: 0,
+			assisted: false,
+
+			paused: false,
+			ticks: 0,
+			tick_time: Duration::ZERO,
+			duration: Duration::ZERO,
 		};
 
-		game.restart();
+		game.reset();
 		game
 	}
 
-	pub fn change_direction(&mut self, direction: Direction) {
-		let opposite = self.direction.opposite();
+	/// Queues a direction change of the given snake for an upcoming tick.
+	///
+	/// Each tick consumes at most one queued direction, so quick presses within a single tick (like up then left to
+	/// make a U-turn) all make it in. A direction is dropped if the queue is full, or if it is the same as or opposite to
+	/// the direction the snake will have by the time it applies.
+	pub fn change_direction(&mut self, snake: usize, direction: Direction) {
+		if self.paused {
+			return;
+		}
+
+		let Some(snake) = self.snakes.get_mut(snake) else {
+			return;
+		};
+
+		let last_direction = snake.input_queue.back().copied().unwrap_or(snake.direction);
+
+		if direction == last_direction || direction == last_direction.opposite() {
+			return;
+		}
 
-		if direction == opposite {
+		if snake.input_queue.len() >= INPUT_QUEUE_LEN {
 			return;
 		}
 
-		self.direction = direction;
+		snake.input_queue.push_back(direction);
 	}
 
-	pub fn update_duration(&mut self) {
-		if self.is_dead {
+	/// Feeds `dt` of elapsed time into the simulation clock.
+	///
+	/// This doesn't run any tick by itself. Call [`update`](Self::update) for as long as [`tick_due`](Self::tick_due)
+	/// says so, so that every tick happens even if a frame took longer than a tick.
+	pub fn advance(&mut self, dt: Duration) {
+		if self.is_over() || self.paused {
 			return;
 		}
 
-		self.duration = self.start.elapsed();
+		self.tick_time += dt;
+	}
+
+	/// Whether enough time has been fed through [`advance`](Self::advance) to run the next tick.
+	pub fn tick_due(&self) -> bool {
+		!self.is_over() && !self.paused && self.tick_time >= self.tick_duration()
+	}
+
+	/// How long the current tick lasts at the snakes' current speed.
+	pub fn tick_duration(&self) -> Duration {
+		// a tick that takes no time would never let the game catch up, and one that takes forever would freeze it
+		let speed = self.speed.clamp(rules::MIN_SPEED, rules::MAX_SPEED) as f64;
+		Duration::try_from_secs_f64(1.0 / speed).unwrap_or(Duration::from_secs(1))
+	}
+
+	/// How much more time has to be fed through [`advance`](Self::advance) before the next tick is due.
+	pub fn time_until_tick(&self) -> Duration {
+		self.tick_duration().saturating_sub(self.tick_time)
+	}
+
+	/// How far along the current tick is, from 0 right after the last one ran to 1 once the next one is due.
+	///
+	/// The game only ever moves the snakes a whole slot at a time, this is for drawing them in between. Once the game
+	/// is over, there's no next tick to get closer to, so it stays at 1.
+	pub fn tick_progress(&self) -> f32 {
+		if self.is_over() {
+			return 1.0;
+		}
+
+		(self.tick_time.as_secs_f32() / self.tick_duration().as_secs_f32()).min(1.0)
 	}
 
+	/// Runs a single tick of the game.
+	///
+	/// All the snakes move at the same time. If any of them dies, none of them moves and the game is over. What kills
+	/// them, what eating a banana does and how else the game ends is up to the [`GameRules`](mode::GameRules) of the
+	/// mode.
 	pub fn update(&mut self) {
-		if self.is_dead {
+		if self.is_over() {
 			return;
 		}
 
-		self.ate_banana = false;
-		self.playfield[self.slot_index(self.snake_head)].set_direction_next(self.direction);
+		let game_rules = self.rules.mode.game_rules();
+		let tick = self.ticks;
+		let tick_duration = self.tick_duration();
+		self.tick_time = self.tick_time.saturating_sub(tick_duration);
+		self.duration += tick_duration;
+		self.ticks += 1;
+		self.events.clear();
+
+		self.despawn_bananas();
+
+		let mut moves = [None; MAX_SNAKES];
+		for (i, next_move) in moves.iter_mut().enumerate().take(self.snakes.len()) {
+			let snake = &mut self.snakes[i];
+
+			if let Some(direction) = snake.input_queue.pop_front() {
+				snake.direction = direction;
+				self.inputs.push(ReplayInput {
+					tick,
+					snake: i as u8,
+					direction,
+				});
+			}
 
-		let next_head = self.next_at(self.snake_head);
+			snake.ate_banana = false;
+			let (head, direction) = (snake.head, snake.direction);
+			self.playfield[self.slot_index(head)].set_direction_next(direction);
 
-		let next_slot = self.playfield[self.slot_index(next_head)];
-		if next_slot.banana().is_some() {
-			// banana eating logic
-			self.ate_banana = true;
+			let next_head = self.next_at(head);
 
-			// snake collision!
-			// Since the tail stays in place, any snake part will make the snake die.
-			if next_slot.has_snake() {
-				self.is_dead = true;
-				return;
+			if game_rules.border(&self.rules) == Border::Solid && !self.contains(next_head) {
+				self.collide(i, DeathCause::Wall, next_head);
+				continue;
 			}
 
-			// push head
-			let curr_slot = &mut self.playfield[self.slot_index(self.snake_head)];
-			curr_slot.set_direction_next(self.direction);
-			curr_slot.set_snake_tail();
+			let next_head = self.wrap_pos(next_head);
+			let next_slot = self.slot_at(next_head);
+			if next_slot.tile() == Tile::Wall {
+				self.collide(i, DeathCause::Wall, next_head);
+				continue;
+			}
 
-			self.snake_head = self.wrap_pos(next_head);
-			let next_slot = &mut self.playfield[self.slot_index(next_head)];
-			next_slot.set_direction_prev(self.direction.opposite());
-			next_slot.set_snake_head();
+			let banana = next_slot.banana();
+			let growth = banana.map_or(0, |banana| self.rules.banana_effect(banana).growth);
 
-			// eat banana
-			next_slot.set_banana(None);
-			self.bananas_eaten += 1;
-			self.speed += 0.1;
+			*next_move = Some(Move {
+				next_head,
+				banana,
+				growing: self.snakes[i].pending_growth > 0 || growth > 0,
+			});
+		}
 
-			self.place_banana();
-		} else {
-			// snake be snakin
+		// snake collision!
+		// A blocked snake doesn't move, which can block the snakes that were about to go where its tail was, so this
+		// goes on until no more snakes get blocked.
+		let mut any_blocked = true;
+		while any_blocked {
+			any_blocked = false;
+
+			for i in 0..self.snakes.len() {
+				let Some(snake_move) = moves[i] else {
+					continue;
+				};
+
+				if self.snakes[i].is_dead() {
+					continue;
+				}
+
+				if let Some(cause) = self.snake_collision(&moves, i, snake_move.next_head) {
+					if self.collide(i, cause, snake_move.next_head) == Collision::Block {
+						moves[i] = None;
+						any_blocked = true;
+					}
+				}
+			}
+		}
+
+		if self.is_dead() {
+			return;
+		}
+
+		// pop tails
+		for (i, snake_move) in moves.iter().enumerate().take(self.snakes.len()) {
+			let Some(snake_move) = *snake_move else {
+				continue;
+			};
+
+			let snake = &mut self.snakes[i];
+			if let Some(banana) = snake_move.banana {
+				snake.ate_banana = true;
+				snake.pending_growth = (snake.pending_growth).saturating_add(self.rules.banana_effect(banana).growth);
+			}
 
-			// snake collision!
-			// Here it's fine if it's just the tail, since we're popping it right after.
-			if next_slot.has_snake_head() {
-				self.is_dead = true;
-				return;
+			if snake.pending_growth > 0 {
+				snake.pending_growth -= 1;
+				snake.len += 1;
+
+				let len = snake.len;
+				self.events.push(GameEvent::Grew { snake: i, len });
+				continue;
 			}
 
-			// pop tail
-			let next_tail = self.next_at(self.snake_tail);
+			let tail = snake.tail;
+			let next_tail = self.wrap_pos(self.next_at(tail));
 
-			let curr_slot = &mut self.playfield[self.slot_index(self.snake_tail)];
+			let curr_slot = &mut self.playfield[self.slot_index(tail)];
 			curr_slot.remove_snake();
+			self.free_slot(tail);
 
-			self.snake_tail = self.wrap_pos(next_tail);
+			self.snakes[i].tail = next_tail;
 			let next_slot = &mut self.playfield[self.slot_index(next_tail)];
 			next_slot.remove_snake();
 			next_slot.set_snake_tail();
+		}
+
+		// push heads
+		for (i, snake_move) in moves.iter().enumerate().take(self.snakes.len()) {
+			let Some(snake_move) = *snake_move else {
+				continue;
+			};
+
+			let (head, direction) = (self.snakes[i].head, self.snakes[i].direction);
 
-			// push head
-			let curr_slot = &mut self.playfield[self.slot_index(self.snake_head)];
-			curr_slot.set_direction_next(self.direction);
+			let curr_slot = &mut self.playfield[self.slot_index(head)];
+			curr_slot.set_direction_next(direction);
 			curr_slot.set_snake_tail();
 
-			self.snake_head = self.wrap_pos(next_head);
-			let next_slot = &mut self.playfield[self.slot_index(next_head)];
-			next_slot.set_direction_prev(self.direction.opposite());
+			self.snakes[i].head = snake_move.next_head;
+			self.take_slot(snake_move.next_head);
+			let next_slot = &mut self.playfield[self.slot_index(snake_move.next_head)];
+			next_slot.set_direction_prev(direction.opposite());
+			next_slot.set_snake_index(i as u8);
 			next_slot.set_snake_head();
+
+			self.events.push(GameEvent::Moved {
+				snake: i,
+				pos: snake_move.next_head,
+				direction,
+			});
+		}
+
+		// eat bananas
+		for (i, snake_move) in moves.iter().enumerate().take(self.snakes.len()) {
+			let Some(Move {
+				next_head,
+				banana: Some(banana),
+				..
+			}) = *snake_move
+			else {
+				continue;
+			};
+
+			self.playfield[self.slot_index(next_head)].set_banana(None);
+			self.bananas.retain(|placed| placed.pos != next_head);
+			self.events.push(GameEvent::AteBanana {
+				snake: i,
+				kind: banana,
+				pos: next_head,
+			});
+
+			let effect = self.rules.banana_effect(banana);
+			let snake = &mut self.snakes[i];
+			snake.score = snake.score.saturating_add(game_rules.points(&effect));
+			snake.bananas_eaten += 1;
+
+			let bananas_eaten = self.snakes.iter().map(|snake| snake.bananas_eaten).sum();
+			self.pending_speed_change += effect.speed_change;
+			self.speed = game_rules.next_speed(&self.rules, self.speed, &mut self.pending_speed_change, bananas_eaten);
+
+			self.schedule_banana_spawn();
+		}
+
+		// checked before the spawns, which can be a few ticks away
+		self.check_playfield_full();
+		self.spawn_due_bananas();
+
+		// the playfield getting filled already said the game was won
+		match self.ending() {
+			Some(Ending::Won) if !self.won => self.events.push(GameEvent::Won),
+			Some(Ending::TimeUp) => self.events.push(GameEvent::TimeUp),
+			_ => {}
 		}
 	}
 
-	pub fn restart(&mut self) {
-		self.playfield.fill(Slot::default());
+	/// What the snake would run into by going to `next_head` along with the other snakes' `moves`, if anything.
+	fn snake_collision(&self, moves: &[Option<Move>; MAX_SNAKES], i: usize, next_head: Pos) -> Option<DeathCause> {
+		// head to head
+		let head_to_head = (moves.iter().enumerate())
+			.any(|(j, other)| j != i && other.is_some_and(|other| other.next_head == next_head));
+
+		if head_to_head {
+			return Some(DeathCause::OtherSnake);
+		}
+
+		// head to body
+		// A tail that moves away this tick is fine to go into, any other snake part is a collision.
+		let next_slot = self.slot_at(next_head);
+		if !next_slot.has_snake() {
+			return None;
+		}
 
-		self.snake_head = pos(self.size.w as i16 / 2, self.size.h as i16 / 2);
-		self.snake_tail = pos(self.snake_head.x - 1, self.snake_head.y);
+		let owner = next_slot.snake_index() as usize;
+		let tail_moves_away = next_slot.is_snake_tail() && moves[owner].is_some_and(|owner_move| !owner_move.growing);
 
-		let head_slot = &mut self.playfield[self.slot_index(self.snake_head)];
-		head_slot.set_direction_prev(Direction::Left);
-		head_slot.set_direction_next(self.direction);
-		head_slot.set_snake_head();
+		match (tail_moves_away, owner == i) {
+			(true, _) => None,
+			(false, true) => Some(DeathCause::SelfCollision),
+			(false, false) => Some(DeathCause::OtherSnake),
+		}
+	}
 
-		let tail_slot = &mut self.playfield[self.slot_index(self.snake_tail)];
-		tail_slot.set_direction_next(Direction::Right);
-		tail_slot.set_snake_tail();
+	/// Freezes the game: no time passes and no tick is due until it is [resumed](Self::resume).
+	pub fn pause(&mut self) {
+		self.paused = true;
+	}
 
-		self.bananas_eaten = 0;
-		self.speed = 3.0;
-		self.direction = Direction::Right;
-		self.is_dead = false;
+	pub fn resume(&mut self) {
+		self.paused = false;
+	}
 
-		self.place_banana();
-		self.start = Instant::now();
-		self.duration = Duration::default();
+	pub fn is_paused(&self) -> bool {
+		self.paused
 	}
 
-	fn place_banana(&mut self) {
-		loop {
-			let banana_pos = rand_pos(&mut self.thread_rng, self.size);
-			let slot = &mut self.playfield[self.slot_index(banana_pos)];
-			if slot.has_snake() {
+	fn reset(&mut self) {
+		for (slot, &tile) in self.playfield.iter_mut().zip(self.level.tiles.iter()) {
+			*slot = Slot::default();
+			slot.set_tile(tile);
+		}
+
+		self.snakes.clear();
+		for i in 0..self.rules.snakes as usize {
+			let start = self.level.starts[i];
+			let snake = Snake::new(start.pos, self.level.start_tail(start), start.direction);
+
+			let head_slot = &mut self.playfield[self.slot_index(snake.head)];
+			head_slot.set_direction_prev(snake.direction.opposite());
+			head_slot.set_direction_next(snake.direction);
+			head_slot.set_snake_index(i as u8);
+			head_slot.set_snake_head();
+
+			let tail_slot = &mut self.playfield[self.slot_index(snake.tail)];
+			tail_slot.set_direction_next(snake.direction);
+			tail_slot.set_snake_index(i as u8);
+			tail_slot.set_snake_tail();
+
+			self.snakes.push(snake);
+		}
+
+		self.free_slots.clear();
+		self.free_slot_indices.fill(NOT_FREE);
+		for i in 0..self.playfield.len() {
+			let slot = self.playfield[i];
+			if slot.tile() == Tile::Floor && !slot.has_snake() {
+				self.free_slot_indices[i] = self.free_slots.len() as u16;
+				self.free_slots.push(i as u16);
+			}
+		}
+
+		self.speed = self.rules.start_speed;
+		self.pending_speed_change = 0.0;
+		self.won = false;
+		self.inputs.clear();
+		self.events.clear();
+		self.rewinds = 0;
+		self.rewound_from = 0;
+		self.assisted = false;
+
+		self.paused = false;
+		self.ticks = 0;
+		self.tick_time = Duration::ZERO;
+		self.duration = Duration::ZERO;
+
+		self.bananas.clear();
+		self.banana_spawns.clear();
+		for _ in 0..self.rules.mode.game_rules().bananas(&self.rules) {
+			self.place_banana();
+		}
+		self.check_playfield_full();
+	}
+
+	/// Wins the game if the snakes fill the whole playfield and there are no bananas left to eat.
+	fn check_playfield_full(&mut self) {
+		if !self.won && self.free_slots.is_empty() && self.bananas.is_empty() {
+			self.won = true;
+			self.events.push(GameEvent::Won);
+		}
+	}
+
+	/// Makes a new banana show up once the spawn delay is over.
+	fn schedule_banana_spawn(&mut self) {
+		self.banana_spawns
+			.push_back(self.ticks + self.rules.banana_spawn_delay as u64);
+	}
+
+	fn spawn_due_bananas(&mut self) {
+		while self.banana_spawns.front().is_some_and(|&tick| tick <= self.ticks) {
+			self.banana_spawns.pop_front();
+			self.place_banana();
+		}
+	}
+
+	/// Takes the bananas that lived long enough off the playfield, and schedules new ones to replace them.
+	fn despawn_bananas(&mut self) {
+		let mut i = 0;
+		while i < self.bananas.len() {
+			let banana = self.bananas[i];
+			if banana.despawn_tick.is_none_or(|tick| tick > self.ticks) {
+				i += 1;
 				continue;
 			}
 
-			let banana = match self.thread_rng.gen_range(0..100) {
-				0 => Banana::Cyan,
-				1..=9 => Banana::Red,
-				_ => Banana::Yellow,
-			};
-			slot.set_banana(Some(banana));
-			break;
+			self.bananas.remove(i);
+			self.playfield[self.slot_index(banana.pos)].set_banana(None);
+			self.free_slot(banana.pos);
+			self.events.push(GameEvent::BananaDespawned {
+				kind: banana.kind,
+				pos: banana.pos,
+			});
+
+			self.schedule_banana_spawn();
+		}
+	}
+
+	/// Places a banana on a random free slot, if there's any left.
+	fn place_banana(&mut self) {
+		if self.free_slots.is_empty() {
+			return;
+		}
+
+		// sampling a u32 rather than a usize keeps the same seed giving the same game on 32-bit platforms
+		let i = self.rng.gen_range(0..self.free_slots.len() as u32) as usize;
+		let slot_index = self.free_slots[i] as usize;
+		self.take_slot_at(slot_index);
+
+		let weights = self.level.banana_weights;
+		let roll = self.rng.gen_range(0..weights.total());
+
+		let banana = if roll < weights.cyan as u32 {
+			Banana::Cyan
+		} else if roll < (weights.cyan + weights.red) as u32 {
+			Banana::Red
+		} else {
+			Banana::Yellow
+		};
+		self.playfield[slot_index].set_banana(Some(banana));
+
+		let pos = pos(
+			(slot_index % self.size.w as usize) as i16,
+			(slot_index / self.size.w as usize) as i16,
+		);
+		let effect = self.rules.banana_effect(banana);
+		let despawn_tick = match self.rules.mode.game_rules().banana_lifetime(&effect) {
+			0 => None,
+			lifetime => Some(self.ticks + lifetime as u64),
+		};
+
+		self.bananas.push(PlacedBanana {
+			kind: banana,
+			pos,
+			despawn_tick,
+		});
+		self.events.push(GameEvent::BananaSpawned { kind: banana, pos });
+	}
+
+	/// Makes a snake run into what's at `pos`. Whether it dies or not is up to the mode.
+	fn collide(&mut self, snake: usize, cause: DeathCause, pos: Pos) -> Collision {
+		let collision = self.rules.mode.game_rules().collision(cause);
+		match collision {
+			Collision::Die => {
+				self.snakes[snake].death_cause = Some(cause);
+				self.snakes[snake].death_pos = Some(pos);
+				self.events.push(GameEvent::Died { snake, cause, pos });
+			}
+			Collision::Block => self.events.push(GameEvent::Blocked { snake, cause, pos }),
 		}
+
+		collision
+	}
+
+	/// Removes the slot at `pos` from the free slots, if it was there.
+	fn take_slot(&mut self, pos: Pos) {
+		self.take_slot_at(self.slot_index(pos));
+	}
+
+	fn take_slot_at(&mut self, slot_index: usize) {
+		let i = self.free_slot_indices[slot_index];
+		if i == NOT_FREE {
+			return;
+		}
+
+		self.free_slots.swap_remove(i as usize);
+		if let Some(&moved) = self.free_slots.get(i as usize) {
+			self.free_slot_indices[moved as usize] = i;
+		}
+		self.free_slot_indices[slot_index] = NOT_FREE;
+	}
+
+	/// Adds the slot at `pos` back to the free slots, if it wasn't already there.
+	fn free_slot(&mut self, pos: Pos) {
+		let slot_index = self.slot_index(pos);
+		if self.free_slot_indices[slot_index] != NOT_FREE {
+			return;
+		}
+
+		self.free_slot_indices[slot_index] = self.free_slots.len() as u16;
+		self.free_slots.push(slot_index as u16);
 	}
 
 	pub fn size(&self) -> Size {
 		self.size
 	}
 
+	pub fn rules(&self) -> &Rules {
+		&self.rules
+	}
+
+	pub fn level(&self) -> &Level {
+		&self.level
+	}
+
 	pub fn slot_at(&self, pos: Pos) -> Slot {
 		self.playfield[self.slot_index(pos)]
 	}
 
-	pub fn snake_head(&self) -> Pos {
-		self.snake_head
+	/// The snakes on the playfield, player one first.
+	pub fn snakes(&self) -> &[Snake] {
+		&self.snakes
 	}
 
-	pub fn ate_banana(&self) -> bool {
-		self.ate_banana
+	/// The bananas on the playfield, oldest first.
+	pub fn bananas(&self) -> &[PlacedBanana] {
+		&self.bananas
 	}
 
+	/// Iterates over the segments of a snake, from its tail to its head.
+	pub fn segments(&self, snake: usize) -> Segments<'_, R> {
+		let snake = &self.snakes[snake];
+
+		Segments {
+			game: self,
+			snake,
+			pos: snake.tail,
+			remaining: snake.len,
+		}
+	}
+
+	/// Everything that happened during the last tick, in order. Right after the game (re)starts, that's the first
+	/// banana showing up.
+	pub fn events(&self) -> &[GameEvent] {
+		&self.events
+	}
+
+	/// Directions the head and the tail of a snake went in during the last tick, for each of them that moved.
+	pub fn last_move(&self, snake: usize) -> (Option<Direction>, Option<Direction>) {
+		let head = self.events.iter().find_map(|event| match *event {
+			GameEvent::Moved {
+				snake: i, direction, ..
+			} if i == snake => Some(direction),
+			_ => None,
+		});
+
+		let grew = (self.events.iter()).any(|event| matches!(*event, GameEvent::Grew { snake: i, .. } if i == snake));
+
+		// the slot the tail is in now still points back to where it came from
+		let tail = head
+			.filter(|_| !grew)
+			.map(|_| self.slot_at(self.snakes[snake].tail()).direction_prev().opposite());
+
+		(head, tail)
+	}
+
+	/// Whether a snake died.
 	pub fn is_dead(&self) -> bool {
-		self.is_dead
+		self.snakes.iter().any(Snake::is_dead)
 	}
 
-	pub fn bananas_eaten(&self) -> u32 {
-		self.bananas_eaten
+	/// Whether the snakes filled the whole playfield, or reached the goal of the mode.
+	pub fn is_won(&self) -> bool {
+		self.won || self.ending() == Some(Ending::Won)
+	}
+
+	/// Whether the snakes filled the whole playfield, as opposed to winning by reaching the goal of the mode.
+	pub fn is_playfield_full(&self) -> bool {
+		self.won
 	}
 
+	/// Whether the mode's time limit ran out.
+	pub fn is_time_up(&self) -> bool {
+		self.ending() == Some(Ending::TimeUp)
+	}
+
+	/// Whether the game ended, either by dying, by winning or by running out of time.
+	pub fn is_over(&self) -> bool {
+		self.is_dead() || self.won || self.ending().is_some()
+	}
+
+	/// How the mode says the game ended, if it did.
+	fn ending(&self) -> Option<Ending> {
+		(self.rules.mode.game_rules()).ending(&self.snakes, self.duration)
+	}
+
+	/// Speed of the snakes, in ticks per second.
 	pub fn speed(&self) -> f32 {
 		self.speed
 	}
 
-	pub fn direction(&self) -> Direction {
-		self.direction
+	/// Number of times the game got [restored](Self::restore) to an earlier point.
+	pub fn rewinds(&self) -> u32 {
+		self.rewinds
+	}
+
+	/// Whether the last tick already ran once before the game got [restored](Self::restore) to an earlier point.
+	pub fn is_replaying(&self) -> bool {
+		self.ticks <= self.rewound_from
 	}
 
+	/// Whether a bot steered any of the snakes at some point of the game. It stays that way even after the bot lets
+	/// go or the game gets rewound to before it took over.
+	pub fn is_assisted(&self) -> bool {
+		self.assisted
+	}
+
+	/// Marks the game as [assisted](Self::is_assisted), for when a bot steers one of the snakes.
+	pub fn mark_assisted(&mut self) {
+		self.assisted = true;
+	}
+
+	/// Number of ticks run since the start of the game.
+	pub fn ticks(&self) -> u64 {
+		self.ticks
+	}
+
+	/// In-game time, made of all the ticks run so far plus the time spent in the current one.
 	pub fn duration(&self) -> Duration {
-		self.duration
+		self.duration + self.tick_time
+	}
+
+	/// A replay of the game so far, if it was started from a seed.
+	pub fn replay(&self) -> Option<Replay> {
+		Some(Replay {
+			seed: self.seed?,
+			level: self.level.clone(),
+			rules: self.rules.clone(),
+			inputs: self.inputs.clone(),
+			ticks: self.ticks,
+			state_hash: self.state_hash(),
+		})
+	}
+
+	/// A hash of everything that makes up the current state of the game.
+	///
+	/// Two games that went through the exact same ticks have the same state hash.
+	pub fn state_hash(&self) -> u64 {
+		// FNV-1a
+		let mut hash = 0xcbf29ce484222325u64;
+		let mut write = |bytes: &[u8]| {
+			for &byte in bytes {
+				hash ^= byte as u64;
+				hash = hash.wrapping_mul(0x100000001b3);
+			}
+		};
+
+		for slot in self.playfield.iter() {
+			write(&slot.0.to_le_bytes());
+		}
+
+		for snake in &self.snakes {
+			write(&snake.head.x.to_le_bytes());
+			write(&snake.head.y.to_le_bytes());
+			write(&snake.tail.x.to_le_bytes());
+			write(&snake.tail.y.to_le_bytes());
+			write(&snake.len.to_le_bytes());
+			write(&[
+				snake.direction as u8,
+				snake.death_cause.map_or(0, |cause| cause as u8 + 1),
+			]);
+			write(&snake.score.to_le_bytes());
+			write(&snake.bananas_eaten.to_le_bytes());
+			write(&snake.pending_growth.to_le_bytes());
+		}
+
+		for banana in &self.bananas {
+			write(&banana.despawn_tick.map_or(0, |tick| tick + 1).to_le_bytes());
+		}
+
+		for spawn_tick in &self.banana_spawns {
+			write(&spawn_tick.to_le_bytes());
+		}
+
+		write(&[self.won as u8]);
+		write(&self.speed.to_bits().to_le_bytes());
+		write(&self.pending_speed_change.to_bits().to_le_bytes());
+		write(&self.ticks.to_le_bytes());
+
+		hash
 	}
 
 	#[inline]
@@ -273,6 +1015,12 @@ impl SnakeGame {
 		pos(x, y)
 	}
 
+	/// Whether the position is inside the playfield, without wrapping around.
+	#[inline]
+	fn contains(&self, p: Pos) -> bool {
+		(0..self.size.w as i16).contains(&p.x) && (0..self.size.h as i16).contains(&p.y)
+	}
+
 	#[inline]
 	fn slot_index(&self, pos: Pos) -> usize {
 		let pos = self.wrap_pos(pos);
@@ -285,18 +1033,79 @@ impl SnakeGame {
 	}
 }
 
+/// A piece of a snake, as yielded by [`SnakeGame::segments`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+	pub pos: Pos,
+	/// Direction the snake was going in when it entered this segment. For the tail, that's the same as `direction_out`.
+	pub direction_in: Direction,
+	/// Direction the snake goes in when leaving this segment. For the head, that's the direction the snake is going.
+	pub direction_out: Direction,
+}
+
+/// Iterator over the segments of a snake, from its tail to its head.
+pub struct Segments<'a, R> {
+	game: &'a SnakeGame<R>,
+	snake: &'a Snake,
+	pos: Pos,
+	remaining: u32,
+}
+
+impl<R: Rng> Iterator for Segments<'_, R> {
+	type Item = Segment;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.remaining == 0 {
+			return None;
+		}
+
+		let slot = self.game.slot_at(self.pos);
+		let is_tail = self.remaining == self.snake.len;
+		let is_head = self.remaining == 1;
+
+		let direction_out = match is_head {
+			true => self.snake.direction,
+			false => slot.direction_next(),
+		};
+		let direction_in = match is_tail {
+			true => direction_out,
+			false => slot.direction_prev().opposite(),
+		};
+
+		let segment = Segment {
+			pos: self.pos,
+			direction_in,
+			direction_out,
+		};
+
+		self.pos = self.game.wrap_pos(self.pos + direction_out.pos_offset());
+		self.remaining -= 1;
+
+		Some(segment)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.remaining as usize, Some(self.remaining as usize))
+	}
+}
+
+impl<R: Rng> ExactSizeIterator for Segments<'_, R> {}
+
 /// A slot on the playfield.
 ///
 /// # Anatomy of a slot type
 ///
 /// ```ignore
-/// xx xx   xx xx
-/// || ||   || ||
-/// || ||   || ++---- direction enum (prev)
-/// || ||   ++------- direction enum (next)
-/// || ||
-/// || ++------------ snake enum
-/// ++--------------- banana enum
+/// -- -- xx xx   xx xx xx xx
+///       || ||   || || || ||
+///       || ||   || || || ++---- direction enum (prev)
+///       || ||   || || ++------- direction enum (next)
+///       || ||   || ||
+///       || ||   || ++---------- snake enum
+///       || ||   ++------------- banana enum
+///       || ||
+///       || ++------------------ tile enum
+///       ++--------------------- snake index
 /// ```
 ///
 /// Direction enum:
@@ -322,9 +1131,18 @@ impl SnakeGame {
 /// | red  (10) | cyan   (11) |
 /// +-------------------------+
 /// ```
+///
+/// Tile enum:
+/// ```ignore
+/// +-----------------------+
+/// | floor (00) | wall (01) |
+/// +-----------------------+
+/// ```
+///
+/// The snake index tells which snake is in the slot, and only means something if there is one.
 #[derive(Clone, Copy, PartialEq, Eq, Default)]
 #[repr(transparent)]
-pub struct Slot(u8);
+pub struct Slot(u16);
 
 impl Slot {
 	#[inline]
@@ -340,7 +1158,7 @@ impl Slot {
 
 	#[inline]
 	pub fn set_direction_prev(&mut self, direction: Direction) {
-		self.0 &= 0b1111_1100;
+		self.0 &= !0b0000_0011;
 		self.0 |= match direction {
 			Direction::Up => 0b0000_0000,
 			Direction::Right => 0b0000_0001,
@@ -362,7 +1180,7 @@ impl Slot {
 
 	#[inline]
 	pub fn set_direction_next(&mut self, direction: Direction) {
-		self.0 &= 0b1111_0011;
+		self.0 &= !0b0000_1100;
 		self.0 |= match direction {
 			Direction::Up => 0b0000_0000,
 			Direction::Right => 0b0000_0100,
@@ -371,19 +1189,15 @@ impl Slot {
 		}
 	}
 
-	#[inline]
-	pub fn has_snake_head(&self) -> bool {
-		self.0 & 0b0001_0000 > 0
-	}
-
 	#[inline]
 	pub fn set_snake_head(&mut self) {
 		self.0 |= 0b0001_0000;
 	}
 
+	/// Whether the slot holds the very end of a snake, rather than any other part of it.
 	#[inline]
-	pub fn has_snake_tail(&self) -> bool {
-		self.0 & 0b0010_0000 > 0
+	pub fn is_snake_tail(&self) -> bool {
+		self.0 & 0b0011_0000 == 0b0010_0000
 	}
 
 	#[inline]
@@ -397,7 +1211,7 @@ impl Slot {
 	}
 
 	pub fn remove_snake(&mut self) {
-		self.0 &= 0b1100_1111;
+		self.0 &= !0b0011_0000;
 	}
 
 	#[inline]
@@ -413,7 +1227,7 @@ impl Slot {
 
 	#[inline]
 	pub fn set_banana(&mut self, banana: Option<Banana>) {
-		self.0 &= 0b0011_1111;
+		self.0 &= !0b1100_0000;
 		self.0 |= match banana {
 			None => 0b0000_0000,
 			Some(Banana::Yellow) => 0b0100_0000,
@@ -421,4 +1235,126 @@ impl Slot {
 			Some(Banana::Cyan) => 0b1100_0000,
 		}
 	}
+
+	#[inline]
+	pub fn tile(&self) -> Tile {
+		match self.0 & 0b0011_0000_0000 {
+			0b0000_0000_0000 => Tile::Floor,
+			0b0001_0000_0000 => Tile::Wall,
+			_ => unreachable!(),
+		}
+	}
+
+	#[inline]
+	pub fn set_tile(&mut self, tile: Tile) {
+		self.0 &= !0b0011_0000_0000;
+		self.0 |= match tile {
+			Tile::Floor => 0b0000_0000_0000,
+			Tile::Wall => 0b0001_0000_0000,
+		}
+	}
+
+	#[inline]
+	pub fn snake_index(&self) -> u8 {
+		((self.0 & 0b1100_0000_0000) >> 10) as u8
+	}
+
+	#[inline]
+	pub fn set_snake_index(&mut self, index: u8) {
+		self.0 &= !0b1100_0000_0000;
+		self.0 |= ((index & 0b11) as u16) << 10;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn new_game(level: &str, rules: Rules) -> SnakeGame {
+		SnakeGame::new(Level::parse(level).unwrap(), rules, 1)
+	}
+
+	#[test]
+	fn input_queue_drops_same_and_opposite_directions() {
+		let mut game = new_game("---\n.....\n..>..\n.....\n", Rules::default());
+
+		game.change_direction(0, Direction::Right);
+		game.change_direction(0, Direction::Left);
+		assert!(game.snakes[0].input_queue.is_empty());
+
+		// directions are checked against the last queued one, not the current one
+		game.change_direction(0, Direction::Up);
+		game.change_direction(0, Direction::Down);
+		game.change_direction(0, Direction::Left);
+		assert_eq!(game.snakes[0].input_queue, [Direction::Up, Direction::Left]);
+	}
+
+	#[test]
+	fn input_queue_is_capped() {
+		let mut game = new_game("---\n.....\n..>..\n.....\n", Rules::default());
+
+		for direction in [Direction::Up, Direction::Left, Direction::Down, Direction::Right] {
+			game.change_direction(0, direction);
+		}
+		assert_eq!(
+			game.snakes[0].input_queue,
+			[Direction::Up, Direction::Left, Direction::Down]
+		);
+
+		game.pause();
+		game.snakes[0].input_queue.clear();
+		game.change_direction(0, Direction::Up);
+		assert!(game.snakes[0].input_queue.is_empty());
+	}
+
+	#[test]
+	fn queued_u_turn_takes_one_tick_per_direction() {
+		let mut game = new_game("---\n.....\n..>..\n.....\n", Rules::default());
+
+		game.change_direction(0, Direction::Up);
+		game.change_direction(0, Direction::Left);
+
+		game.update();
+		assert_eq!(
+			(game.snakes[0].direction, game.snakes[0].head),
+			(Direction::Up, pos(2, 0))
+		);
+		game.update();
+		assert_eq!(
+			(game.snakes[0].direction, game.snakes[0].head),
+			(Direction::Left, pos(1, 0))
+		);
+		assert!(game.snakes[0].input_queue.is_empty());
+	}
+
+	#[test]
+	fn filling_the_playfield_wins_whatever_the_spawn_delay() {
+		for banana_spawn_delay in [0, 2] {
+			let mut rules = Rules {
+				banana_spawn_delay,
+				..Rules::default()
+			};
+			// the snake grows by more than there's room for, so it only lives if eating the last banana ends the game
+			for effect in [&mut rules.yellow_banana, &mut rules.red_banana, &mut rules.cyan_banana] {
+				effect.growth = 3;
+			}
+
+			// goes around the 3x2 playfield clockwise
+			let mut game = new_game("---\n.>.\n...\n", rules);
+			while !game.is_over() && game.ticks() < 1000 {
+				let head = game.snakes[0].head;
+				let direction = match (head.x, head.y) {
+					(2, 0) => Direction::Down,
+					(_, 0) => Direction::Right,
+					(0, 1) => Direction::Up,
+					_ => Direction::Left,
+				};
+				game.change_direction(0, direction);
+				game.update();
+			}
+
+			assert!(game.is_playfield_full(), "spawn delay {banana_spawn_delay}");
+			assert!(!game.is_dead(), "spawn delay {banana_spawn_delay}");
+		}
+	}
 }
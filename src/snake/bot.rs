@@ -0,0 +1,429 @@
+use std::collections::VecDeque;
+
+use super::level::Level;
+use super::rules::Border;
+use super::{Direction, SnakeGame, Tile};
+use crate::math::pos::{pos, Pos};
+
+/// Maximum number of steps [`HamiltonianBot`] spends looking for a cycle on a level with walls.
+const CYCLE_SEARCH_BUDGET: u32 = 20_000;
+
+/// Names of the built-in bots, in the order the game cycles through them.
+pub const BOT_NAMES: [&str; 2] = ["greedy", "cycle"];
+
+/// Creates one of the built-in bots from its [name](SnakeController::name).
+pub fn bot_by_name(name: &str) -> Option<Box<dyn SnakeController>> {
+	match name {
+		"greedy" => Some(Box::new(GreedyBot::default())),
+		"cycle" => Some(Box::new(HamiltonianBot::default())),
+		_ => None,
+	}
+}
+
+/// Something that steers a snake in place of a player.
+pub trait SnakeController {
+	/// Short name to show to the player.
+	fn name(&self) -> &'static str;
+
+	/// Picks the direction the snake should go in on the next tick.
+	///
+	/// Called right before each tick, with the game as it is before the tick.
+	fn next_direction(&mut self, game: &SnakeGame, snake: usize) -> Direction;
+}
+
+/// Goes for the closest banana by the shortest path.
+///
+/// It doesn't think ahead, so it happily traps itself once it gets long enough. When no banana is in reach, it goes
+/// wherever there is the most room left.
+#[derive(Debug, Default)]
+pub struct GreedyBot {
+	/// Direction of the first step on the way to each slot visited by the search.
+	first_steps: Vec<Option<Direction>>,
+	queue: VecDeque<Pos>,
+}
+
+impl SnakeController for GreedyBot {
+	fn name(&self) -> &'static str {
+		"greedy"
+	}
+
+	fn next_direction(&mut self, game: &SnakeGame, snake: usize) -> Direction {
+		let snake = &game.snakes()[snake];
+		let head = snake.head();
+
+		self.first_steps.clear();
+		self.first_steps.resize(game.playfield.len(), None);
+		self.queue.clear();
+
+		for direction in Direction::ALL {
+			if let Some(next) = step(game, head, direction).filter(|&next| !is_blocked(game, next)) {
+				self.first_steps[game.slot_index(next)] = Some(direction);
+				self.queue.push_back(next);
+			}
+		}
+
+		while let Some(current) = self.queue.pop_front() {
+			let Some(first_step) = self.first_steps[game.slot_index(current)] else {
+				continue;
+			};
+
+			if game.slot_at(current).banana().is_some() {
+				return first_step;
+			}
+
+			for direction in Direction::ALL {
+				let Some(next) = step(game, current, direction) else {
+					continue;
+				};
+
+				let next_index = game.slot_index(next);
+				if next != head && self.first_steps[next_index].is_none() && !is_blocked(game, next) {
+					self.first_steps[next_index] = Some(first_step);
+					self.queue.push_back(next);
+				}
+			}
+		}
+
+		(Direction::ALL.into_iter())
+			.filter_map(|direction| {
+				let next = step(game, head, direction).filter(|&next| !is_blocked(game, next))?;
+				Some((room_at(game, next), direction))
+			})
+			.max_by_key(|&(room, _)| room)
+			.map_or(snake.direction(), |(_, direction)| direction)
+	}
+}
+
+/// Goes around a cycle that passes through every slot of the playfield exactly once, so it never runs into itself.
+///
+/// Such a cycle doesn't exist on every level. On the ones where it can't find one, it plays like a [`GreedyBot`].
+#[derive(Debug, Default)]
+pub struct HamiltonianBot {
+	cycle: Option<Cycle>,
+	fallback: GreedyBot,
+}
+
+#[derive(Debug)]
+struct Cycle {
+	level: Level,
+	border: Border,
+	/// The two directions the cycle goes in from each slot, if there is a cycle.
+	links: Option<Box<[[Direction; 2]]>>,
+}
+
+impl SnakeController for HamiltonianBot {
+	fn name(&self) -> &'static str {
+		"cycle"
+	}
+
+	fn next_direction(&mut self, game: &SnakeGame, snake: usize) -> Direction {
+		let border = game.rules().border;
+		let cycle = match &mut self.cycle {
+			Some(cycle) if cycle.level == game.level && cycle.border == border => cycle,
+			cycle => cycle.insert(Cycle {
+				level: game.level.clone(),
+				border,
+				links: find_cycle(game).map(|cycle| cycle_links(game, &cycle)),
+			}),
+		};
+
+		if let Some(links) = &cycle.links {
+			let head = game.snakes()[snake].head();
+
+			// The snake comes from one side of the cycle, so the other side is where it goes.
+			// On the first tick, the snake isn't on the cycle yet and both sides might be free. Either will do.
+			let direction = (links[game.slot_index(head)].into_iter())
+				.find(|&direction| step(game, head, direction).is_some_and(|next| !is_blocked(game, next)));
+
+			if let Some(direction) = direction {
+				return direction;
+			}
+		}
+
+		self.fallback.next_direction(game, snake)
+	}
+}
+
+/// Position one step away in the given direction, or `None` if that's outside of a playfield with a solid border.
+fn step(game: &SnakeGame, from: Pos, direction: Direction) -> Option<Pos> {
+	let next = from + direction.pos_offset();
+
+	match game.rules().border {
+		Border::Solid if !game.contains(next) => None,
+		_ => Some(game.wrap_pos(next)),
+	}
+}
+
+fn is_blocked(game: &SnakeGame, pos: Pos) -> bool {
+	let slot = game.slot_at(pos);
+	slot.tile() == Tile::Wall || slot.has_snake()
+}
+
+/// Number of slots that can be reached from `from` without going through a wall or a snake.
+fn room_at(game: &SnakeGame, from: Pos) -> usize {
+	let mut visited = vec![false; game.playfield.len()];
+	let mut stack = vec![from];
+	visited[game.slot_index(from)] = true;
+
+	let mut room = 0;
+	while let Some(current) = stack.pop() {
+		room += 1;
+
+		for direction in Direction::ALL {
+			let Some(next) = step(game, current, direction) else {
+				continue;
+			};
+
+			let next_index = game.slot_index(next);
+			if !visited[next_index] && !is_blocked(game, next) {
+				visited[next_index] = true;
+				stack.push(next);
+			}
+		}
+	}
+
+	room
+}
+
+/// Looks for a cycle that goes through every floor slot, ignoring snakes and bananas.
+///
+/// Empty playfields get one of a few well-known cycles. Levels with walls go through a search that gives up after a
+/// while, since there might not be any cycle at all.
+fn find_cycle(game: &SnakeGame) -> Option<Vec<Pos>> {
+	let (w, h) = (game.size.w as i16, game.size.h as i16);
+
+	let floor: Vec<Pos> = (0..h)
+		.flat_map(|y| (0..w).map(move |x| pos(x, y)))
+		.filter(|&p| game.slot_at(p).tile() == Tile::Floor)
+		.collect();
+
+	if floor.len() == game.playfield.len() {
+		let rows = grid_cycles(w, h).map(|cycle| cycle.into_iter().map(|(x, y)| pos(x, y)).collect::<Vec<_>>());
+		let columns = grid_cycles(h, w).map(|cycle| cycle.into_iter().map(|(y, x)| pos(x, y)).collect::<Vec<_>>());
+
+		if let Some(cycle) = rows
+			.into_iter()
+			.chain(columns)
+			.find(|cycle| is_cycle(game, cycle, floor.len()))
+		{
+			return Some(cycle);
+		}
+	}
+
+	if !colors_balance(game, &floor) {
+		return None;
+	}
+
+	let start = *floor.first()?;
+	let mut path = vec![start];
+	let mut visited = vec![false; game.playfield.len()];
+	visited[game.slot_index(start)] = true;
+
+	let mut budget = CYCLE_SEARCH_BUDGET;
+	extend_path(game, &mut path, &mut visited, floor.len(), &mut budget).then_some(path)
+}
+
+/// Whether a cycle could go through all of `floor` as far as checkerboard coloring can tell.
+///
+/// On a playfield where the slots can be colored like a checkerboard, every step goes from one color to the other, so a
+/// cycle needs as many slots of each color. Playfields that wrap around with an odd size can't be colored like that, and
+/// there's no telling without searching.
+fn colors_balance(game: &SnakeGame, floor: &[Pos]) -> bool {
+	let mut colors = vec![None; game.playfield.len()];
+	let mut counts = [0usize; 2];
+
+	for &start in floor {
+		if colors[game.slot_index(start)].is_some() {
+			continue;
+		}
+
+		colors[game.slot_index(start)] = Some(0);
+		let mut stack = vec![start];
+
+		while let Some(current) = stack.pop() {
+			let color = colors[game.slot_index(current)].unwrap_or(0);
+			counts[color] += 1;
+
+			for direction in Direction::ALL {
+				let Some(next) = step(game, current, direction) else {
+					continue;
+				};
+
+				if game.slot_at(next).tile() == Tile::Wall {
+					continue;
+				}
+
+				match colors[game.slot_index(next)] {
+					None => {
+						colors[game.slot_index(next)] = Some(1 - color);
+						stack.push(next);
+					}
+					Some(next_color) if next_color == color => return true,
+					Some(_) => {}
+				}
+			}
+		}
+	}
+
+	counts[0] == counts[1]
+}
+
+/// Cycles through a `w` by `h` grid, one going back along the first column and one that wraps around.
+///
+/// They only work out for some sizes and borders, which [`is_cycle`] tells.
+fn grid_cycles(w: i16, h: i16) -> [Vec<(i16, i16)>; 2] {
+	// snake through the rows, leaving the first column free for the way back
+	let mut back_along_column = Vec::new();
+	for y in 0..h {
+		match y % 2 {
+			0 => back_along_column.extend((1..w).map(|x| (x, y))),
+			_ => back_along_column.extend((1..w).rev().map(|x| (x, y))),
+		}
+	}
+	back_along_column.extend((0..h).rev().map(|y| (0, y)));
+
+	// go through each row to the right, wrapping around, then down to the next one
+	let wrapping = (0..h)
+		.flat_map(|y| (0..w).map(move |i| ((i - y).rem_euclid(w), y)))
+		.collect();
+
+	[back_along_column, wrapping]
+}
+
+/// Whether `cycle` goes through `floor_count` different floor slots, each one a step away from the previous one.
+fn is_cycle(game: &SnakeGame, cycle: &[Pos], floor_count: usize) -> bool {
+	if cycle.len() != floor_count {
+		return false;
+	}
+
+	let mut visited = vec![false; game.playfield.len()];
+	for (i, &p) in cycle.iter().enumerate() {
+		let next = cycle[(i + 1) % cycle.len()];
+
+		if std::mem::replace(&mut visited[game.slot_index(p)], true) || game.slot_at(p).tile() == Tile::Wall {
+			return false;
+		}
+
+		if !Direction::ALL
+			.into_iter()
+			.any(|direction| step(game, p, direction) == Some(next))
+		{
+			return false;
+		}
+	}
+
+	true
+}
+
+/// Extends the path one slot at a time until it goes through every floor slot and can loop back to its start.
+///
+/// Slots with the fewest ways out are tried first, which finds a cycle much faster than trying them in any order.
+fn extend_path(
+	game: &SnakeGame,
+	path: &mut Vec<Pos>,
+	visited: &mut [bool],
+	floor_count: usize,
+	budget: &mut u32,
+) -> bool {
+	let last = path[path.len() - 1];
+
+	if path.len() == floor_count {
+		return Direction::ALL
+			.into_iter()
+			.any(|direction| step(game, last, direction) == Some(path[0]));
+	}
+
+	if *budget == 0 {
+		return false;
+	}
+	*budget -= 1;
+
+	let unvisited_neighbors = |p: Pos| -> Vec<Pos> {
+		(Direction::ALL.into_iter())
+			.filter_map(|direction| step(game, p, direction))
+			.filter(|&next| !visited[game.slot_index(next)] && game.slot_at(next).tile() == Tile::Floor)
+			.collect()
+	};
+
+	let mut candidates: Vec<(usize, Pos)> = (unvisited_neighbors(last).into_iter())
+		.map(|next| (unvisited_neighbors(next).len(), next))
+		.collect();
+	candidates.sort_by_key(|&(ways_out, _)| ways_out);
+
+	for (_, next) in candidates {
+		visited[game.slot_index(next)] = true;
+		path.push(next);
+
+		// Every slot left has to be entered and left again. If one of the slots next to `last` can't anymore, there's
+		// no point in going further down this path.
+		let stranded = (Direction::ALL.into_iter())
+			.filter_map(|direction| step(game, last, direction))
+			.filter(|&p| !visited[game.slot_index(p)] && game.slot_at(p).tile() == Tile::Floor)
+			.any(|p| {
+				let ways = (Direction::ALL.into_iter())
+					.filter_map(|direction| step(game, p, direction))
+					.filter(|&q| {
+						q == next
+							|| q == path[0] || (!visited[game.slot_index(q)] && game.slot_at(q).tile() == Tile::Floor)
+					})
+					.count();
+				ways < 2
+			});
+
+		if !stranded && extend_path(game, path, visited, floor_count, budget) {
+			return true;
+		}
+
+		path.pop();
+		visited[game.slot_index(next)] = false;
+	}
+
+	false
+}
+
+/// For each slot of the playfield, the directions to the slots before and after it on the cycle.
+fn cycle_links(game: &SnakeGame, cycle: &[Pos]) -> Box<[[Direction; 2]]> {
+	let direction_to = |from: Pos, to: Pos| {
+		(Direction::ALL.into_iter())
+			.find(|&direction| step(game, from, direction) == Some(to))
+			.unwrap_or(Direction::Up)
+	};
+
+	let mut links = vec![[Direction::Up; 2]; game.playfield.len()];
+	for (i, &p) in cycle.iter().enumerate() {
+		let prev = cycle[(i + cycle.len() - 1) % cycle.len()];
+		let next = cycle[(i + 1) % cycle.len()];
+
+		links[game.slot_index(p)] = [direction_to(p, next), direction_to(p, prev)];
+	}
+
+	links.into_boxed_slice()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::snake::Rules;
+
+	#[test]
+	fn cycle_bot_fills_a_small_wrap_playfield() {
+		let level = Level::parse("---\n......\n......\n..>...\n......\n......\n......\n").unwrap();
+		let rules = Rules {
+			border: Border::Wrap,
+			..Rules::default()
+		};
+
+		for seed in 0..5 {
+			let mut game = SnakeGame::new(level.clone(), rules.clone(), seed);
+			let mut bot = HamiltonianBot::default();
+
+			while !game.is_over() && game.ticks() < 10_000 {
+				let direction = bot.next_direction(&game, 0);
+				game.change_direction(0, direction);
+				game.update();
+			}
+
+			assert!(game.is_playfield_full() && !game.is_dead(), "seed {seed}");
+		}
+	}
+}
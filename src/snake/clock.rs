@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+/// A monotonic source of time that drives a [`SnakeGame`](super::SnakeGame).
+pub trait TimeSource {
+	/// Time elapsed since some fixed point in the past.
+	fn now(&self) -> Duration;
+}
+
+/// Real time, as told by [`Instant`].
+#[derive(Debug, Clone, Copy)]
+pub struct WallClock {
+	origin: Instant,
+}
+
+impl Default for WallClock {
+	fn default() -> Self {
+		Self { origin: Instant::now() }
+	}
+}
+
+impl TimeSource for WallClock {
+	fn now(&self) -> Duration {
+		self.origin.elapsed()
+	}
+}
+
+/// Turns the readings of a [`TimeSource`] into deltas to feed to [`SnakeGame::advance`](super::SnakeGame::advance).
+#[derive(Debug, Clone)]
+pub struct Clock<T = WallClock> {
+	source: T,
+	last: Duration,
+}
+
+impl<T: TimeSource> Clock<T> {
+	pub fn new(source: T) -> Self {
+		let last = source.now();
+		Self { source, last }
+	}
+
+	/// Time elapsed since the last call to `delta`.
+	pub fn delta(&mut self) -> Duration {
+		let now = self.source.now();
+		let delta = now.saturating_sub(self.last);
+		self.last = now;
+		delta
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+	use std::rc::Rc;
+
+	use super::*;
+	use crate::math::size::size;
+	use crate::snake::{Level, Rules, SnakeGame};
+
+	/// Time that only passes when the test says so.
+	#[derive(Debug, Clone, Default)]
+	struct ManualClock(Rc<Cell<Duration>>);
+
+	impl TimeSource for ManualClock {
+		fn now(&self) -> Duration {
+			self.0.get()
+		}
+	}
+
+	#[test]
+	fn ticks_are_due_once_enough_time_went_by() {
+		let time = ManualClock::default();
+		let mut clock = Clock::new(time.clone());
+		let mut game: SnakeGame = SnakeGame::new(Level::empty(size(11, 11)), Rules::default(), 1);
+		let tick = game.tick_duration();
+
+		time.0.set(tick / 2);
+		game.advance(clock.delta());
+		assert!(!game.tick_due());
+		assert_eq!(game.time_until_tick(), tick - tick / 2);
+
+		// a frame that took two ticks runs both of them to catch up, and the half tick from before carries over
+		time.0.set(tick * 5 / 2);
+		game.advance(clock.delta());
+		let mut ticks = 0;
+		while game.tick_due() {
+			game.update();
+			ticks += 1;
+		}
+		assert_eq!((ticks, game.ticks()), (2, 2));
+
+		// the clock doesn't move by itself, so neither does the game
+		game.advance(clock.delta());
+		assert!(!game.tick_due());
+	}
+}
@@ -0,0 +1,249 @@
+//! Tiny helpers to read and write the compact binary formats Snaek stores on disk.
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+	/// The data doesn't start with the magic bytes of the expected format.
+	BadMagic,
+	/// The data was written with a version of the format we don't know about.
+	UnsupportedVersion(u8),
+	/// The data ended before we were done reading it.
+	UnexpectedEof,
+	/// There is more data after the end of what we were reading.
+	TrailingBytes,
+	/// Some value in the data doesn't make sense.
+	Invalid(&'static str),
+	/// The data doesn't match its checksum.
+	Corrupted,
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DecodeError::BadMagic => write!(f, "not a file Snaek knows how to read"),
+			DecodeError::UnsupportedVersion(v) => write!(f, "unsupported format version {v}"),
+			DecodeError::UnexpectedEof => write!(f, "file is truncated"),
+			DecodeError::TrailingBytes => write!(f, "file has unexpected data at the end"),
+			DecodeError::Invalid(what) => write!(f, "invalid {what}"),
+			DecodeError::Corrupted => write!(f, "file is corrupted"),
+		}
+	}
+}
+
+impl Error for DecodeError {}
+
+#[derive(Debug, Default)]
+pub struct Encoder {
+	buf: Vec<u8>,
+}
+
+impl Encoder {
+	pub fn new(magic: &[u8], version: u8) -> Self {
+		let mut encoder = Self::default();
+		encoder.bytes(magic);
+		encoder.u8(version);
+		encoder
+	}
+
+	pub fn bytes(&mut self, bytes: &[u8]) {
+		self.buf.extend_from_slice(bytes);
+	}
+
+	pub fn u8(&mut self, n: u8) {
+		self.buf.push(n);
+	}
+
+	pub fn u16(&mut self, n: u16) {
+		self.bytes(&n.to_le_bytes());
+	}
+
+	pub fn u64(&mut self, n: u64) {
+		self.bytes(&n.to_le_bytes());
+	}
+
+	pub fn f32(&mut self, n: f32) {
+		self.bytes(&n.to_bits().to_le_bytes());
+	}
+
+	/// Writes an unsigned LEB128 variable-length integer.
+	pub fn varint(&mut self, mut n: u64) {
+		loop {
+			let byte = (n & 0x7f) as u8;
+			n >>= 7;
+
+			if n == 0 {
+				self.u8(byte);
+				break;
+			}
+
+			self.u8(byte | 0x80);
+		}
+	}
+
+	/// Writes a checksum of everything written so far.
+	pub fn checksum(&mut self) {
+		self.u64(fnv1a(&self.buf));
+	}
+
+	pub fn finish(self) -> Vec<u8> {
+		self.buf
+	}
+}
+
+#[derive(Debug)]
+pub struct Decoder<'a> {
+	/// Everything that's being decoded, including what has already been read.
+	all: &'a [u8],
+	data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+	/// Starts decoding `data`, checking its magic bytes and returning the format version.
+	pub fn new(data: &'a [u8], magic: &[u8]) -> Result<(Self, u8), DecodeError> {
+		let mut decoder = Self { all: data, data };
+
+		if decoder.bytes(magic.len()).ok() != Some(magic) {
+			return Err(DecodeError::BadMagic);
+		}
+
+		let version = decoder.u8()?;
+		Ok((decoder, version))
+	}
+
+	pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+		if self.data.len() < len {
+			return Err(DecodeError::UnexpectedEof);
+		}
+
+		let (bytes, rest) = self.data.split_at(len);
+		self.data = rest;
+		Ok(bytes)
+	}
+
+	fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+		let mut array = [0; N];
+		array.copy_from_slice(self.bytes(N)?);
+		Ok(array)
+	}
+
+	pub fn u8(&mut self) -> Result<u8, DecodeError> {
+		Ok(self.array::<1>()?[0])
+	}
+
+	pub fn u16(&mut self) -> Result<u16, DecodeError> {
+		Ok(u16::from_le_bytes(self.array()?))
+	}
+
+	pub fn u64(&mut self) -> Result<u64, DecodeError> {
+		Ok(u64::from_le_bytes(self.array()?))
+	}
+
+	pub fn f32(&mut self) -> Result<f32, DecodeError> {
+		Ok(f32::from_bits(u32::from_le_bytes(self.array()?)))
+	}
+
+	/// Reads an unsigned LEB128 variable-length integer.
+	pub fn varint(&mut self) -> Result<u64, DecodeError> {
+		let mut n = 0u64;
+
+		for shift in (0..64).step_by(7) {
+			let byte = self.u8()?;
+			n |= ((byte & 0x7f) as u64) << shift;
+
+			if byte & 0x80 == 0 {
+				return Ok(n);
+			}
+		}
+
+		Err(DecodeError::Invalid("variable-length integer"))
+	}
+
+	/// Reads a checksum written by [`Encoder::checksum`] and makes sure it matches everything read before it.
+	pub fn checksum(&mut self) -> Result<(), DecodeError> {
+		let read = &self.all[..self.all.len() - self.data.len()];
+
+		match self.u64()? == fnv1a(read) {
+			true => Ok(()),
+			false => Err(DecodeError::Corrupted),
+		}
+	}
+
+	/// Makes sure all the data has been read.
+	pub fn finish(self) -> Result<(), DecodeError> {
+		match self.data.is_empty() {
+			true => Ok(()),
+			false => Err(DecodeError::TrailingBytes),
+		}
+	}
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+	let mut hash = 0xcbf29ce484222325u64;
+	for &byte in bytes {
+		hash ^= byte as u64;
+		hash = hash.wrapping_mul(0x100000001b3);
+	}
+	hash
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn codec_round_trip() {
+		let varints = [0, 1, 0x7f, 0x80, 0x3fff, 0x4000, u32::MAX as u64, u64::MAX];
+
+		let mut encoder = Encoder::new(b"TEST", 3);
+		encoder.u8(0xab);
+		encoder.u16(0xbeef);
+		encoder.u64(0x0123_4567_89ab_cdef);
+		encoder.f32(-1.5);
+		for n in varints {
+			encoder.varint(n);
+		}
+		encoder.checksum();
+		let data = encoder.finish();
+
+		let (mut decoder, version) = Decoder::new(&data, b"TEST").unwrap();
+		assert_eq!(version, 3);
+		assert_eq!(decoder.u8().unwrap(), 0xab);
+		assert_eq!(decoder.u16().unwrap(), 0xbeef);
+		assert_eq!(decoder.u64().unwrap(), 0x0123_4567_89ab_cdef);
+		assert_eq!(decoder.f32().unwrap(), -1.5);
+		for n in varints {
+			assert_eq!(decoder.varint().unwrap(), n);
+		}
+		decoder.checksum().unwrap();
+		decoder.finish().unwrap();
+	}
+
+	#[test]
+	fn bad_data_is_rejected() {
+		let mut encoder = Encoder::new(b"TEST", 1);
+		encoder.varint(300);
+		encoder.checksum();
+		let data = encoder.finish();
+
+		assert!(Decoder::new(&data, b"NOPE").is_err());
+
+		let (mut decoder, _) = Decoder::new(&data[..data.len() - 1], b"TEST").unwrap();
+		decoder.varint().unwrap();
+		assert!(decoder.checksum().is_err());
+
+		let mut corrupted = data.clone();
+		corrupted[5] ^= 1;
+		let (mut decoder, _) = Decoder::new(&corrupted, b"TEST").unwrap();
+		decoder.varint().unwrap();
+		assert!(decoder.checksum().is_err());
+
+		let mut trailing = data.clone();
+		trailing.push(0);
+		let (mut decoder, _) = Decoder::new(&trailing, b"TEST").unwrap();
+		decoder.varint().unwrap();
+		decoder.checksum().unwrap();
+		assert!(decoder.finish().is_err());
+	}
+}
@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use rand::{Rng, SeedableRng};
+
+use super::codec::{DecodeError, Decoder, Encoder};
+use super::level::{builtin_levels, Level};
+use super::mode::Mode;
+use super::rules::{Border, Difficulty, Rules};
+use super::SnakeGame;
+use crate::date::Date;
+use crate::storage::Stored;
+
+const DAILY_MAGIC: &[u8] = b"SNKD";
+const DAILY_VERSION: u8 = 1;
+
+/// Seed of the daily challenge of that day.
+///
+/// Days go by in the player's time zone, so a new challenge comes out at their midnight, and everyone gets the same
+/// challenge on the same date.
+pub fn daily_seed(date: Date) -> u64 {
+	date.days() as u64
+}
+
+/// Level of the daily challenge of that day. Each of the built-in levels gets its turn.
+pub fn daily_level(date: Date) -> Level {
+	let mut levels = builtin_levels();
+	let index = date.days().rem_euclid(levels.len() as i32) as usize;
+	levels.swap_remove(index)
+}
+
+/// Rules every daily challenge is played with, whatever rules the player picked for their other games.
+pub fn daily_rules() -> Rules {
+	let mut rules = Rules {
+		mode: Mode::Classic,
+		border: Border::Wrap,
+		snakes: 1,
+		bananas: 1,
+		banana_spawn_delay: 0,
+		..Rules::default()
+	};
+	Difficulty::Normal.apply(&mut rules);
+	rules
+}
+
+impl<R: Rng + SeedableRng> SnakeGame<R> {
+	/// Creates the daily challenge of that day. It's the same game for everyone who plays it on that day.
+	pub fn daily(date: Date) -> Self {
+		Self::new(daily_level(date), daily_rules(), daily_seed(date))
+	}
+}
+
+impl<R> SnakeGame<R> {
+	/// Whether the game is the daily challenge of that day.
+	pub fn is_daily(&self, date: Date) -> bool {
+		self.seed == Some(daily_seed(date)) && self.rules == daily_rules() && self.level == daily_level(date)
+	}
+}
+
+/// How the scored attempt at the daily challenge of one day went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DailyResult {
+	pub date: Date,
+	/// Score of the game, which stays at 0 if it never got to the end.
+	pub score: u32,
+	pub duration: Duration,
+	/// Whether the attempt is over, either because the game ended or because it got abandoned.
+	pub finished: bool,
+}
+
+/// The results of every daily challenge played on this computer, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DailyHistory {
+	results: Vec<DailyResult>,
+}
+
+impl DailyHistory {
+	pub fn result(&self, date: Date) -> Option<&DailyResult> {
+		let index = self.results.binary_search_by_key(&date, |result| result.date).ok()?;
+		Some(&self.results[index])
+	}
+
+	/// Uses up the scored attempt of that day. Returns whether it was still there to use.
+	///
+	/// The attempt counts as soon as the game starts, so that starting it over doesn't give a second chance.
+	pub fn start(&mut self, date: Date) -> bool {
+		let Err(index) = self.results.binary_search_by_key(&date, |result| result.date) else {
+			return false;
+		};
+
+		let result = DailyResult {
+			date,
+			score: 0,
+			duration: Duration::ZERO,
+			finished: false,
+		};
+		self.results.insert(index, result);
+		true
+	}
+
+	/// Records how the scored attempt of that day went, once it's over.
+	pub fn finish(&mut self, date: Date, score: u32, duration: Duration) {
+		if let Some(result) = self.result_mut(date).filter(|result| !result.finished) {
+			result.score = score;
+			result.duration = duration;
+			result.finished = true;
+		}
+	}
+
+	/// Ends the scored attempt of that day with a score of 0 if it's not over yet, because another game took its
+	/// place.
+	pub fn abandon(&mut self, date: Date) {
+		if let Some(result) = self.result_mut(date) {
+			result.finished = true;
+		}
+	}
+
+	fn result_mut(&mut self, date: Date) -> Option<&mut DailyResult> {
+		let index = self.results.binary_search_by_key(&date, |result| result.date).ok()?;
+		Some(&mut self.results[index])
+	}
+
+	/// Number of days in a row a daily challenge was played, up to `today`.
+	///
+	/// Today's challenge not being played yet doesn't break the streak, there's still time to keep it going.
+	pub fn streak(&self, today: Date) -> u32 {
+		let last_day = match self.result(today) {
+			Some(_) => today.days(),
+			None => today.days() - 1,
+		};
+
+		let mut streak = 0;
+		for result in self
+			.results
+			.iter()
+			.rev()
+			.skip_while(|result| result.date.days() > last_day)
+		{
+			if result.date.days() != last_day - streak as i32 {
+				break;
+			}
+
+			streak += 1;
+		}
+
+		streak
+	}
+}
+
+impl Stored for DailyHistory {
+	const WHAT: &'static str = "daily challenge history";
+
+	/// Serializes the history.
+	///
+	/// # Format
+	///
+	/// ```ignore
+	/// "SNKD" version:u8 result_count:varint
+	/// (days_since_1970:varint score:varint duration_ms:varint finished:u8)*
+	/// checksum:u64
+	/// ```
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut encoder = Encoder::new(DAILY_MAGIC, DAILY_VERSION);
+
+		encoder.varint(self.results.len() as u64);
+		for result in &self.results {
+			encoder.varint(result.date.days().max(0) as u64);
+			encoder.varint(result.score as u64);
+			encoder.varint(result.duration.as_millis() as u64);
+			encoder.u8(result.finished as u8);
+		}
+
+		encoder.checksum();
+		encoder.finish()
+	}
+
+	fn from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+		let (mut decoder, version) = Decoder::new(data, DAILY_MAGIC)?;
+		if version != DAILY_VERSION {
+			return Err(DecodeError::UnsupportedVersion(version));
+		}
+
+		let result_count = decoder.varint()?;
+
+		let mut results: Vec<DailyResult> = Vec::new();
+		for _ in 0..result_count {
+			let date = Date::from_days((decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("date"))?);
+			if results.last().is_some_and(|last| last.date >= date) {
+				return Err(DecodeError::Invalid("date order"));
+			}
+
+			let score = (decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("score"))?;
+			let duration = Duration::from_millis(decoder.varint()?);
+			let finished = match decoder.u8()? {
+				0 => false,
+				1 => true,
+				_ => return Err(DecodeError::Invalid("finished flag")),
+			};
+
+			results.push(DailyResult {
+				date,
+				score,
+				duration,
+				finished,
+			});
+		}
+
+		decoder.checksum()?;
+		decoder.finish()?;
+
+		Ok(Self { results })
+	}
+}
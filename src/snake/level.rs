@@ -0,0 +1,417 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use super::codec::{DecodeError, Decoder, Encoder};
+use super::{Direction, Tile, MAX_SNAKES};
+use crate::math::pos::{pos, Pos};
+use crate::math::size::{size, Size};
+
+/// Biggest playfield a level can have, in tiles. That's all the room the window has for it.
+const MAX_LEVEL_SIZE: Size = size(11, 11);
+
+const BUILTIN_LEVELS: &[&str] = &[
+	include_str!("../../levels/classic.snaeklevel"),
+	include_str!("../../levels/box.snaeklevel"),
+	include_str!("../../levels/pillars.snaeklevel"),
+	include_str!("../../levels/corridors.snaeklevel"),
+];
+
+/// The levels that ship with the game.
+pub fn builtin_levels() -> Vec<Level> {
+	(BUILTIN_LEVELS.iter())
+		.map(|text| Level::parse(text).expect("built-in levels should be valid"))
+		.collect()
+}
+
+/// Relative chances of each kind of banana to spawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BananaWeights {
+	pub yellow: u16,
+	pub red: u16,
+	pub cyan: u16,
+}
+
+impl Default for BananaWeights {
+	fn default() -> Self {
+		Self {
+			yellow: 90,
+			red: 9,
+			cyan: 1,
+		}
+	}
+}
+
+/// Where a snake starts on a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnakeStart {
+	/// Where the head of the snake starts. Its tail is right behind it.
+	pub pos: Pos,
+	pub direction: Direction,
+}
+
+/// The layout a [`SnakeGame`](super::SnakeGame) starts from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Level {
+	pub name: String,
+	pub size: Size,
+	pub tiles: Box<[Tile]>,
+	/// Where each snake starts, player one first. There is always at least one.
+	pub starts: Vec<SnakeStart>,
+	pub banana_weights: BananaWeights,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLevelError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl fmt::Display for ParseLevelError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl Error for ParseLevelError {}
+
+impl Level {
+	/// An empty playfield with the snake in the middle, going right.
+	pub fn empty(playfield_size: Size) -> Self {
+		Self {
+			name: "Empty".to_string(),
+			size: playfield_size,
+			tiles: vec![Tile::Floor; playfield_size.w as usize * playfield_size.h as usize].into_boxed_slice(),
+			starts: vec![SnakeStart {
+				pos: pos((playfield_size.w / 2) as i16, (playfield_size.h / 2) as i16),
+				direction: Direction::Right,
+			}],
+			banana_weights: BananaWeights::default(),
+		}
+	}
+
+	/// Parses a level from its text format.
+	///
+	/// # Format
+	///
+	/// ```text
+	/// # Lines starting with a '#' are comments.
+	/// name: Pillars
+	/// bananas: yellow 90, red 9, cyan 1
+	/// ---
+	/// ...........
+	/// ..#.....#..
+	/// ...........
+	/// .....>.....
+	/// ```
+	///
+	/// The header is a list of `key: value` lines, all of them optional. The only thing it says about bananas is how
+	/// likely each kind is to spawn, with `bananas`. How many of them there are, how long they take to show up and how
+	/// long they stay are up to the [rules](super::Rules) of the game, not the level.
+	///
+	/// The header is followed by a `---` line and the grid of the playfield, where `.` is floor, `#` is a wall, and one
+	/// of `^`, `>`, `v` or `<` is the head of a snake facing that direction. The tail of a snake starts right behind
+	/// its head. The grid ends at the first blank line, after which there can only be more blank lines.
+	///
+	/// There can be up to 2 snakes. The first one in reading order belongs to player one, and is the only one used in
+	/// single-player games.
+	pub fn parse(text: &str) -> Result<Self, ParseLevelError> {
+		let mut level = Self::empty(Size::ZERO);
+		let mut starts = Vec::new();
+		let mut rows: Vec<Vec<Tile>> = Vec::new();
+
+		let mut in_grid = false;
+		let mut grid_ended = false;
+		for (i, line) in text.lines().enumerate() {
+			let line_num = i + 1;
+			let error = |message: String| ParseLevelError {
+				line: line_num,
+				message,
+			};
+
+			let line = line.trim_end();
+
+			if !in_grid {
+				if line.is_empty() || line.starts_with('#') {
+					continue;
+				}
+
+				if line == "---" {
+					in_grid = true;
+					continue;
+				}
+
+				let Some((key, value)) = line.split_once(':') else {
+					return Err(error(format!("expected `key: value` or `---`, got {line:?}")));
+				};
+
+				match key.trim() {
+					"name" => level.name = value.trim().to_string(),
+					"bananas" => level.banana_weights = parse_banana_weights(value).map_err(error)?,
+					key => return Err(error(format!("unknown key {key:?}"))),
+				}
+
+				continue;
+			}
+
+			if line.is_empty() {
+				grid_ended = !rows.is_empty();
+				continue;
+			}
+
+			if grid_ended {
+				return Err(error(
+					"there should be nothing but blank lines after the grid".to_string(),
+				));
+			}
+
+			let mut row = Vec::with_capacity(line.len());
+			for (x, c) in line.chars().enumerate() {
+				let direction = match c {
+					'.' => None,
+					'#' => {
+						row.push(Tile::Wall);
+						continue;
+					}
+					'^' => Some(Direction::Up),
+					'>' => Some(Direction::Right),
+					'v' => Some(Direction::Down),
+					'<' => Some(Direction::Left),
+					c => return Err(error(format!("unknown tile {c:?}"))),
+				};
+
+				if let Some(direction) = direction {
+					if starts.len() >= MAX_SNAKES {
+						return Err(error(format!("there can only be up to {MAX_SNAKES} snakes")));
+					}
+
+					starts.push(SnakeStart {
+						pos: pos(x as i16, rows.len() as i16),
+						direction,
+					});
+				}
+
+				row.push(Tile::Floor);
+			}
+
+			if let Some(first_row) = rows.first() {
+				if row.len() != first_row.len() {
+					return Err(error("all rows of the grid should have the same width".to_string()));
+				}
+			}
+
+			rows.push(row);
+		}
+
+		let last_line = text.lines().count();
+		let error = |message: &str| ParseLevelError {
+			line: last_line,
+			message: message.to_string(),
+		};
+
+		let (width, height) = (rows.first().map_or(0, |row| row.len()), rows.len());
+		if width < 2 || height < 1 || width > MAX_LEVEL_SIZE.w as usize || height > MAX_LEVEL_SIZE.h as usize {
+			let (max_w, max_h) = (MAX_LEVEL_SIZE.w, MAX_LEVEL_SIZE.h);
+			return Err(error(&format!(
+				"the grid should be between 2x1 and {max_w}x{max_h} tiles"
+			)));
+		}
+
+		if starts.is_empty() {
+			return Err(error("the grid has no snake in it"));
+		}
+
+		level.size = size(width as u16, height as u16);
+		level.tiles = rows.into_iter().flatten().collect();
+		level.starts = starts;
+
+		if let Err(message) = level.check_starts() {
+			return Err(error(message));
+		}
+
+		Ok(level)
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+		let text = std::fs::read_to_string(path)?;
+		Ok(Self::parse(&text)?)
+	}
+
+	/// Where the tail of a snake starts, wrapping around the playfield.
+	pub fn start_tail(&self, start: SnakeStart) -> Pos {
+		let tail = start.pos - start.direction.pos_offset();
+		let (w, h) = (self.size.w as i16, self.size.h as i16);
+		pos(tail.x.rem_euclid(w), tail.y.rem_euclid(h))
+	}
+
+	/// Makes sure every snake starts on the floor, without overlapping another one.
+	fn check_starts(&self) -> Result<(), &'static str> {
+		let mut taken = Vec::with_capacity(self.starts.len() * 2);
+
+		for &start in &self.starts {
+			for pos in [start.pos, self.start_tail(start)] {
+				if self.tile_at(pos) != Tile::Floor {
+					return Err("snakes should start on the floor");
+				}
+
+				if taken.contains(&pos) {
+					return Err("snakes shouldn't overlap");
+				}
+
+				taken.push(pos);
+			}
+		}
+
+		Ok(())
+	}
+
+	pub fn tile_at(&self, pos: Pos) -> Tile {
+		self.tiles[pos.y as usize * self.size.w as usize + pos.x as usize]
+	}
+
+	pub fn encode(&self, encoder: &mut Encoder) {
+		encoder.varint(self.name.len() as u64);
+		encoder.bytes(self.name.as_bytes());
+
+		encoder.u16(self.size.w);
+		encoder.u16(self.size.h);
+		encoder.u8(self.starts.len() as u8);
+		for start in &self.starts {
+			encoder.u16(start.pos.x as u16);
+			encoder.u16(start.pos.y as u16);
+			encoder.u8(start.direction as u8);
+		}
+
+		encoder.u16(self.banana_weights.yellow);
+		encoder.u16(self.banana_weights.red);
+		encoder.u16(self.banana_weights.cyan);
+
+		// 4 tiles per byte
+		for tiles in self.tiles.chunks(4) {
+			let mut byte = 0;
+			for (i, &tile) in tiles.iter().enumerate() {
+				byte |= (tile as u8) << (i * 2);
+			}
+			encoder.u8(byte);
+		}
+	}
+
+	pub fn decode(decoder: &mut Decoder) -> Result<Self, DecodeError> {
+		let name_len = decoder.varint()? as usize;
+		let name =
+			String::from_utf8(decoder.bytes(name_len)?.to_vec()).map_err(|_| DecodeError::Invalid("level name"))?;
+
+		let size = size(decoder.u16()?, decoder.u16()?);
+		if size.w < 2 || size.h < 1 || size.w > MAX_LEVEL_SIZE.w || size.h > MAX_LEVEL_SIZE.h {
+			return Err(DecodeError::Invalid("level size"));
+		}
+
+		let start_count = decoder.u8()? as usize;
+		if start_count == 0 || start_count > MAX_SNAKES {
+			return Err(DecodeError::Invalid("snake count"));
+		}
+
+		let mut starts = Vec::with_capacity(start_count);
+		for _ in 0..start_count {
+			let (x, y) = (decoder.u16()?, decoder.u16()?);
+			if x >= size.w || y >= size.h {
+				return Err(DecodeError::Invalid("snake start position"));
+			}
+
+			starts.push(SnakeStart {
+				pos: pos(x as i16, y as i16),
+				direction: Direction::from_bits(decoder.u8()?),
+			});
+		}
+
+		let banana_weights = BananaWeights {
+			yellow: decoder.u16()?,
+			red: decoder.u16()?,
+			cyan: decoder.u16()?,
+		};
+
+		let tile_count = size.w as usize * size.h as usize;
+		let mut tiles = Vec::with_capacity(tile_count);
+		for &byte in decoder.bytes(tile_count.div_ceil(4))? {
+			for i in 0..4 {
+				let tile = match (byte >> (i * 2)) & 0b11 {
+					0 => Tile::Floor,
+					1 => Tile::Wall,
+					_ => return Err(DecodeError::Invalid("tile")),
+				};
+				tiles.push(tile);
+			}
+		}
+		tiles.truncate(tile_count);
+
+		let level = Self {
+			name,
+			size,
+			tiles: tiles.into_boxed_slice(),
+			starts,
+			banana_weights,
+		};
+
+		if level.banana_weights.total() == 0 {
+			return Err(DecodeError::Invalid("banana weights"));
+		}
+
+		if level.check_starts().is_err() {
+			return Err(DecodeError::Invalid("snake start position"));
+		}
+
+		Ok(level)
+	}
+}
+
+impl BananaWeights {
+	pub fn total(&self) -> u32 {
+		self.yellow as u32 + self.red as u32 + self.cyan as u32
+	}
+}
+
+/// Parses something like `yellow 90, red 9, cyan 1`. Bananas that aren't mentioned never spawn.
+fn parse_banana_weights(value: &str) -> Result<BananaWeights, String> {
+	let mut weights = BananaWeights {
+		yellow: 0,
+		red: 0,
+		cyan: 0,
+	};
+
+	for entry in value.split(',') {
+		let mut words = entry.split_whitespace();
+
+		let (Some(banana), Some(weight), None) = (words.next(), words.next(), words.next()) else {
+			return Err(format!("expected `<banana> <weight>`, got {:?}", entry.trim()));
+		};
+
+		let weight = (weight.parse::<u16>()).map_err(|_| format!("invalid banana weight {weight:?}"))?;
+
+		match banana {
+			"yellow" => weights.yellow = weight,
+			"red" => weights.red = weight,
+			"cyan" => weights.cyan = weight,
+			banana => return Err(format!("unknown banana {banana:?}")),
+		}
+	}
+
+	if weights.total() == 0 {
+		return Err("at least one banana should be able to spawn".to_string());
+	}
+
+	Ok(weights)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn blank_lines_can_end_the_grid() {
+		let level = Level::parse("---\n.>.\n...\n\n  \n").unwrap();
+		assert_eq!(level.size, size(3, 2));
+
+		let error = Level::parse("---\n.>.\n\n...\n").unwrap_err();
+		assert_eq!(error.line, 4);
+	}
+}
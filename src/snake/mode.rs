@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use super::rules::{BananaEffect, Border, Rules};
+use super::{DeathCause, Snake};
+
+/// How long a time attack lasts.
+pub const TIME_ATTACK_DURATION: Duration = Duration::from_secs(120);
+
+/// Number of bananas a snake has to eat to win in banana goal mode.
+pub const BANANA_GOAL: u32 = 30;
+
+/// The ways to play the game, each with its own [`GameRules`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+	#[default]
+	Classic,
+	Zen,
+	TimeAttack,
+	BananaGoal,
+}
+
+impl Mode {
+	pub const ALL: [Self; 4] = [Self::Classic, Self::Zen, Self::TimeAttack, Self::BananaGoal];
+
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Classic => "Classic",
+			Self::Zen => "Zen",
+			Self::TimeAttack => "Time attack",
+			Self::BananaGoal => "Banana goal",
+		}
+	}
+
+	/// The mode after this one, going back to classic after the last one.
+	pub fn next(self) -> Self {
+		Self::ALL[(self as usize + 1) % Self::ALL.len()]
+	}
+
+	pub fn game_rules(self) -> &'static dyn GameRules {
+		match self {
+			Self::Classic => &ClassicRules,
+			Self::Zen => &ZenRules,
+			Self::TimeAttack => &TimeAttackRules,
+			Self::BananaGoal => &BananaGoalRules,
+		}
+	}
+}
+
+/// What happens to a snake that runs into something.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collision {
+	/// The snake dies, and the game is over.
+	Die,
+	/// The snake doesn't move this tick, and can turn away on the next one.
+	Block,
+}
+
+/// How a game ends, besides all the snakes filling the whole playfield.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ending {
+	Won,
+	TimeUp,
+}
+
+/// What sets a mode apart from the others, as hooks [`SnakeGame::update`](super::SnakeGame::update) calls into.
+///
+/// Every hook defaults to how the classic game plays, so a mode only needs to override what it changes. The hooks
+/// must only depend on their arguments, or replays of the mode won't play back the same.
+pub trait GameRules {
+	/// What happens at the edge of the playfield.
+	fn border(&self, rules: &Rules) -> Border {
+		rules.border
+	}
+
+	/// What happens to a snake that runs into something that would kill it for this cause.
+	fn collision(&self, _cause: DeathCause) -> Collision {
+		Collision::Die
+	}
+
+	/// Points a snake gets for eating a banana.
+	fn points(&self, effect: &BananaEffect) -> u32 {
+		effect.points
+	}
+
+	/// The speed after a banana got eaten. See [`Rules::next_speed`].
+	fn next_speed(&self, rules: &Rules, speed: f32, pending_speed_change: &mut f32, bananas_eaten: u32) -> f32 {
+		rules.next_speed(speed, pending_speed_change, bananas_eaten)
+	}
+
+	/// Number of bananas on the playfield at the same time.
+	fn bananas(&self, rules: &Rules) -> u8 {
+		rules.bananas
+	}
+
+	/// Number of ticks a banana stays on the playfield if nobody eats it, or 0 for it to stay there forever.
+	fn banana_lifetime(&self, effect: &BananaEffect) -> u32 {
+		effect.lifetime
+	}
+
+	/// Whether the game ends after a tick, and how. `duration` is the in-game time once that tick is over.
+	fn ending(&self, _snakes: &[Snake], _duration: Duration) -> Option<Ending> {
+		None
+	}
+}
+
+/// The game as it's always been: don't crash, and eat as many bananas as you can.
+pub struct ClassicRules;
+
+impl GameRules for ClassicRules {}
+
+/// Nothing can kill the snakes, and nothing gets faster. Bananas stay until they get eaten.
+pub struct ZenRules;
+
+impl GameRules for ZenRules {
+	fn collision(&self, _cause: DeathCause) -> Collision {
+		Collision::Block
+	}
+
+	fn next_speed(&self, _rules: &Rules, speed: f32, pending_speed_change: &mut f32, _bananas_eaten: u32) -> f32 {
+		*pending_speed_change = 0.0;
+		speed
+	}
+
+	fn banana_lifetime(&self, _effect: &BananaEffect) -> u32 {
+		0
+	}
+}
+
+/// The classic game, but it stops after [`TIME_ATTACK_DURATION`]. The best score by then wins.
+pub struct TimeAttackRules;
+
+impl GameRules for TimeAttackRules {
+	fn ending(&self, _snakes: &[Snake], duration: Duration) -> Option<Ending> {
+		(duration >= TIME_ATTACK_DURATION).then_some(Ending::TimeUp)
+	}
+}
+
+/// The classic game, won by the first snake to eat [`BANANA_GOAL`] bananas.
+pub struct BananaGoalRules;
+
+impl GameRules for BananaGoalRules {
+	fn ending(&self, snakes: &[Snake], _duration: Duration) -> Option<Ending> {
+		(snakes.iter())
+			.any(|snake| snake.bananas_eaten() >= BANANA_GOAL)
+			.then_some(Ending::Won)
+	}
+}
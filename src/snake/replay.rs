@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use super::codec::{DecodeError, Decoder, Encoder};
+use super::level::Level;
+use super::rules::Rules;
+use super::{Direction, SnakeGame, MAX_SNAKES};
+use crate::storage::Stored;
+
+// inputs keep a single bit for the snake they belong to
+const _: () = assert!(MAX_SNAKES <= 2);
+
+const REPLAY_MAGIC: &[u8] = b"SNKR";
+const REPLAY_VERSION: u8 = 1;
+
+/// A direction change that got applied to a snake on a given tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayInput {
+	pub tick: u64,
+	pub snake: u8,
+	pub direction: Direction,
+}
+
+/// Everything needed to play a game again exactly as it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+	pub seed: u64,
+	pub level: Level,
+	pub rules: Rules,
+	pub inputs: Vec<ReplayInput>,
+	/// Number of ticks the recorded game ran for.
+	pub ticks: u64,
+	/// [`SnakeGame::state_hash`] of the game at the end of the recording.
+	pub state_hash: u64,
+}
+
+impl Stored for Replay {
+	const WHAT: &'static str = "replay";
+
+	/// Serializes the replay into its compact binary format.
+	///
+	/// # Format
+	///
+	/// ```ignore
+	/// "SNKR" version:u8 seed:u64 level rules ticks:varint state_hash:u64
+	/// input_count:varint (tick_delta << 3 | snake << 2 | direction):varint*
+	/// ```
+	///
+	/// All fixed-size integers are little-endian. Input ticks are stored as the difference with the previous input's
+	/// tick, which keeps most of them on a single byte.
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut encoder = Encoder::new(REPLAY_MAGIC, REPLAY_VERSION);
+
+		encoder.u64(self.seed);
+		self.level.encode(&mut encoder);
+		self.rules.encode(&mut encoder);
+		encoder.varint(self.ticks);
+		encoder.u64(self.state_hash);
+
+		encode_inputs(&mut encoder, &self.inputs);
+
+		encoder.finish()
+	}
+
+	fn from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+		let (mut decoder, version) = Decoder::new(data, REPLAY_MAGIC)?;
+
+		if version != REPLAY_VERSION {
+			return Err(DecodeError::UnsupportedVersion(version));
+		}
+
+		let seed = decoder.u64()?;
+		let level = Level::decode(&mut decoder)?;
+
+		let rules = Rules::decode(&mut decoder)?;
+		if rules.snakes as usize > level.starts.len() {
+			return Err(DecodeError::Invalid("snake count"));
+		}
+
+		let ticks = decoder.varint()?;
+		let state_hash = decoder.u64()?;
+
+		let inputs = decode_inputs(&mut decoder, ticks, rules.snakes)?;
+
+		decoder.finish()?;
+
+		Ok(Self {
+			seed,
+			level,
+			rules,
+			inputs,
+			ticks,
+			state_hash,
+		})
+	}
+}
+
+/// Writes the inputs of a game as their count followed by one varint per input.
+pub(super) fn encode_inputs(encoder: &mut Encoder, inputs: &[ReplayInput]) {
+	encoder.varint(inputs.len() as u64);
+
+	let mut last_tick = 0;
+	for input in inputs {
+		encoder.varint(((input.tick - last_tick) << 3) | (input.snake as u64) << 2 | input.direction as u64);
+		last_tick = input.tick;
+	}
+}
+
+/// Reads inputs written by [`encode_inputs`] for a game that ran for `ticks` ticks with `snakes` snakes.
+pub(super) fn decode_inputs(decoder: &mut Decoder, ticks: u64, snakes: u8) -> Result<Vec<ReplayInput>, DecodeError> {
+	let input_count = decoder.varint()?;
+	let mut inputs = Vec::new();
+
+	let mut last_tick = 0u64;
+	for _ in 0..input_count {
+		let n = decoder.varint()?;
+
+		let tick = (last_tick.checked_add(n >> 3))
+			.filter(|&tick| tick < ticks)
+			.ok_or(DecodeError::Invalid("input tick"))?;
+
+		let snake = ((n >> 2) & 1) as u8;
+		if snake >= snakes {
+			return Err(DecodeError::Invalid("input snake"));
+		}
+
+		inputs.push(ReplayInput {
+			tick,
+			snake,
+			direction: Direction::from_bits(n as u8),
+		});
+		last_tick = tick;
+	}
+
+	Ok(inputs)
+}
+
+/// Plays a [`Replay`] back through the same [`SnakeGame::update`] path as a live game.
+pub struct ReplayPlayer {
+	replay: Replay,
+	game: SnakeGame,
+	next_input: usize,
+	playing: bool,
+}
+
+impl ReplayPlayer {
+	pub fn new(replay: Replay) -> Self {
+		let game = SnakeGame::new(replay.level.clone(), replay.rules.clone(), replay.seed);
+
+		Self {
+			replay,
+			game,
+			next_input: 0,
+			playing: true,
+		}
+	}
+
+	pub fn replay(&self) -> &Replay {
+		&self.replay
+	}
+
+	pub fn game(&self) -> &SnakeGame {
+		&self.game
+	}
+
+	pub fn is_playing(&self) -> bool {
+		self.playing
+	}
+
+	pub fn toggle_playing(&mut self) {
+		self.playing = !self.playing;
+	}
+
+	/// Whether the playback reached the end of the recording.
+	pub fn is_finished(&self) -> bool {
+		self.game.ticks() >= self.replay.ticks || self.game.is_over()
+	}
+
+	/// Whether the playback reached the end of the recording in a different state than the recorded game.
+	///
+	/// That should never happen. If it does, the game isn't as deterministic as it should be.
+	pub fn is_desynced(&self) -> bool {
+		self.is_finished() && self.game.state_hash() != self.replay.state_hash
+	}
+
+	/// Plays the replay forward by `dt`, if it's playing.
+	pub fn advance(&mut self, dt: Duration) {
+		if !self.playing || self.is_finished() {
+			return;
+		}
+
+		self.game.advance(dt);
+		while self.game.tick_due() && !self.is_finished() {
+			self.step();
+		}
+	}
+
+	/// Jumps to the given tick of the replay.
+	///
+	/// Seeking backwards plays the whole replay again from the start, which is cheap enough since ticks don't do much.
+	pub fn seek(&mut self, tick: u64) {
+		let tick = tick.min(self.replay.ticks);
+
+		if tick < self.game.ticks() {
+			self.game = SnakeGame::new(self.replay.level.clone(), self.replay.rules.clone(), self.replay.seed);
+			self.next_input = 0;
+		}
+
+		while self.game.ticks() < tick && !self.is_finished() {
+			self.step();
+		}
+	}
+
+	fn step(&mut self) {
+		let tick = self.game.ticks();
+
+		while let Some(input) = self.replay.inputs.get(self.next_input) {
+			if input.tick != tick {
+				break;
+			}
+
+			self.game.change_direction(input.snake as usize, input.direction);
+			self.next_input += 1;
+		}
+
+		self.game.update();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::snake::{bot_by_name, builtin_levels, BOT_NAMES};
+
+	#[test]
+	fn replay_round_trip_plays_back_without_desync() {
+		let level = builtin_levels()
+			.into_iter()
+			.find(|level| level.starts.len() >= 2)
+			.unwrap();
+
+		for seed in 0..6 {
+			let rules = Rules {
+				snakes: 1 + seed as u8 % 2,
+				..Rules::default()
+			};
+			let mut game = SnakeGame::new(level.clone(), rules, seed);
+			let mut bots: Vec<_> = (BOT_NAMES.iter()).map(|&name| bot_by_name(name).unwrap()).collect();
+
+			while !game.is_over() && game.ticks() < 2000 {
+				for (snake, bot) in bots.iter_mut().enumerate().take(game.snakes().len()) {
+					let direction = bot.next_direction(&game, snake);
+					game.change_direction(snake, direction);
+				}
+				game.update();
+			}
+
+			let replay = game.replay().unwrap();
+			let decoded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+			assert_eq!(decoded, replay);
+
+			let mut player = ReplayPlayer::new(decoded);
+			player.seek(replay.ticks);
+			assert!(player.is_finished() && !player.is_desynced(), "seed {seed}");
+			assert_eq!(player.game().ticks(), game.ticks());
+		}
+	}
+}
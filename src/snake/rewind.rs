@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::{PlacedBanana, Slot, Snake, SnakeGame};
+
+/// Everything about a [`SnakeGame`] that changes from one tick to the next, to go back to it later.
+///
+/// The level and the rules never change during a game, so they're left out. Inputs only ever get added, so only
+/// their count is kept.
+#[derive(Clone)]
+pub struct Snapshot<R> {
+	rng: R,
+	playfield: Box<[Slot]>,
+	free_slots: Vec<u16>,
+	free_slot_indices: Box<[u16]>,
+	snakes: Vec<Snake>,
+	bananas: Vec<PlacedBanana>,
+	banana_spawns: VecDeque<u64>,
+	speed: f32,
+	pending_speed_change: f32,
+	won: bool,
+	input_count: usize,
+	ticks: u64,
+	duration: Duration,
+}
+
+impl<R: Clone> SnakeGame<R> {
+	pub fn snapshot(&self) -> Snapshot<R> {
+		Snapshot {
+			rng: self.rng.clone(),
+			playfield: self.playfield.clone(),
+			free_slots: self.free_slots.clone(),
+			free_slot_indices: self.free_slot_indices.clone(),
+			snakes: self.snakes.clone(),
+			bananas: self.bananas.clone(),
+			banana_spawns: self.banana_spawns.clone(),
+			speed: self.speed,
+			pending_speed_change: self.pending_speed_change,
+			won: self.won,
+			input_count: self.inputs.len(),
+			ticks: self.ticks,
+			duration: self.duration,
+		}
+	}
+
+	/// Takes the game back to a snapshot of it, as if nothing happened since. The game stays marked as
+	/// [rewound](Self::rewinds) though.
+	///
+	/// The snapshot has to come from this very game, and from before now.
+	pub fn restore(&mut self, snapshot: &Snapshot<R>) {
+		self.rewound_from = self.rewound_from.max(self.ticks);
+		self.rng = snapshot.rng.clone();
+		self.playfield.clone_from(&snapshot.playfield);
+		self.free_slots.clone_from(&snapshot.free_slots);
+		self.free_slot_indices.clone_from(&snapshot.free_slot_indices);
+		self.snakes.clone_from(&snapshot.snakes);
+		self.bananas.clone_from(&snapshot.bananas);
+		self.banana_spawns.clone_from(&snapshot.banana_spawns);
+		self.speed = snapshot.speed;
+		self.pending_speed_change = snapshot.pending_speed_change;
+		self.won = snapshot.won;
+		self.inputs.truncate(snapshot.input_count);
+		self.events.clear();
+		self.ticks = snapshot.ticks;
+		self.tick_time = Duration::ZERO;
+		self.duration = snapshot.duration;
+		self.rewinds += 1;
+	}
+}
+
+/// The last few ticks of a game, to rewind it.
+pub struct RewindBuffer<R> {
+	snapshots: VecDeque<Snapshot<R>>,
+	capacity: usize,
+}
+
+impl<R: Clone> RewindBuffer<R> {
+	/// Creates a buffer that remembers up to `capacity` ticks.
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			snapshots: VecDeque::with_capacity(capacity),
+			capacity,
+		}
+	}
+
+	/// Remembers the game as it is now. Call it before every tick.
+	///
+	/// Snapshots from after now get forgotten, which happens after rewinding or restarting the game.
+	pub fn push(&mut self, game: &SnakeGame<R>) {
+		while (self.snapshots.back()).is_some_and(|snapshot| snapshot.ticks >= game.ticks) {
+			self.snapshots.pop_back();
+		}
+
+		if self.snapshots.len() >= self.capacity {
+			self.snapshots.pop_front();
+		}
+		self.snapshots.push_back(game.snapshot());
+	}
+
+	/// Takes the game back by `by` of in-game time, or as far back as the buffer goes. Returns whether there was
+	/// anything to go back to.
+	pub fn rewind(&mut self, game: &mut SnakeGame<R>, by: Duration) -> bool {
+		let target = game.duration.saturating_sub(by);
+		let index = (self.snapshots.iter())
+			.rposition(|snapshot| snapshot.duration <= target)
+			.unwrap_or(0);
+
+		let Some(snapshot) = self.snapshots.get(index) else {
+			return false;
+		};
+
+		game.restore(snapshot);
+		self.snapshots.truncate(index + 1);
+		true
+	}
+
+	pub fn clear(&mut self) {
+		self.snapshots.clear();
+	}
+}
@@ -0,0 +1,63 @@
+use rand::{Error, RngCore, SeedableRng};
+
+/// The random number generator used by default in a [`SnakeGame`](super::SnakeGame).
+///
+/// It's a [SplitMix64](https://prng.di.unimi.it/splitmix64.c) generator. It's tiny, fast, and most importantly
+/// its whole state is a single `u64`, so the same seed always gives the same game on every platform and every
+/// version of `rand`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnaekRng {
+	state: u64,
+}
+
+impl SnaekRng {
+	pub const fn new(seed: u64) -> Self {
+		Self { state: seed }
+	}
+
+	/// The whole state of the generator. Passing it to [`new`](Self::new) gives back the exact same generator.
+	pub const fn state(&self) -> u64 {
+		self.state
+	}
+}
+
+impl RngCore for SnaekRng {
+	#[inline]
+	fn next_u32(&mut self) -> u32 {
+		(self.next_u64() >> 32) as u32
+	}
+
+	#[inline]
+	fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+		z ^ (z >> 31)
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		for chunk in dest.chunks_mut(8) {
+			let bytes = self.next_u64().to_le_bytes();
+			chunk.copy_from_slice(&bytes[..chunk.len()]);
+		}
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+		self.fill_bytes(dest);
+		Ok(())
+	}
+}
+
+impl SeedableRng for SnaekRng {
+	type Seed = [u8; 8];
+
+	fn from_seed(seed: Self::Seed) -> Self {
+		Self::new(u64::from_le_bytes(seed))
+	}
+
+	fn seed_from_u64(seed: u64) -> Self {
+		Self::new(seed)
+	}
+}
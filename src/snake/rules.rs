@@ -0,0 +1,388 @@
+use super::codec::{DecodeError, Decoder, Encoder};
+use super::mode::Mode;
+use super::{Banana, MAX_SNAKES};
+
+/// Slowest the snakes can go, in tiles per second.
+pub const MIN_SPEED: f32 = 0.5;
+
+/// Fastest the snakes can go, in tiles per second, whatever the rules say. Any faster and a frame would have to run
+/// more ticks than it can keep up with.
+pub const MAX_SPEED: f32 = 100.0;
+
+/// What happens when the snake goes past the edge of the playfield.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Border {
+	/// The snake comes back from the other side.
+	#[default]
+	Wrap,
+	/// The snake dies.
+	Solid,
+}
+
+impl Border {
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Wrap => "Wrap",
+			Self::Solid => "Solid",
+		}
+	}
+}
+
+/// How the speed of the snakes changes as they eat bananas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpeedCurve {
+	/// Every banana changes the speed by its [`speed_change`](BananaEffect::speed_change).
+	#[default]
+	Linear,
+	/// The speed only changes once every that many bananas, by all of their speed changes at once.
+	Stepped(u32),
+	/// Like [`Linear`](Self::Linear), but the closer the speed gets to the maximum speed, the less bananas speed the
+	/// snakes up. It never quite gets there.
+	Capped,
+}
+
+/// What happens when the snake eats a banana.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BananaEffect {
+	/// Points added to the score.
+	pub points: u32,
+	/// How many tiles longer the snake gets.
+	pub growth: u32,
+	/// How much faster the snake goes, in tiles per second. Negative values slow it down, but never below the start
+	/// speed.
+	pub speed_change: f32,
+	/// Number of ticks the banana stays on the playfield if nobody eats it, or 0 for it to stay there forever.
+	pub lifetime: u32,
+}
+
+impl BananaEffect {
+	/// What a plain yellow banana does.
+	const CLASSIC: Self = Self {
+		points: 1,
+		growth: 1,
+		speed_change: 0.1,
+		lifetime: 0,
+	};
+}
+
+/// The rules a [`SnakeGame`](super::SnakeGame) is played with.
+///
+/// They are fixed for the whole duration of a game, and recorded in its replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+	/// How the game is played and how it ends.
+	pub mode: Mode,
+	/// Speed of the snake at the start of a game, in tiles per second. Between [`MIN_SPEED`] and [`MAX_SPEED`].
+	pub start_speed: f32,
+	/// How the speed changes as bananas get eaten.
+	pub speed_curve: SpeedCurve,
+	/// Speed the snake never goes past, in tiles per second. Can be infinite, in which case [`MAX_SPEED`] is the
+	/// limit.
+	pub max_speed: f32,
+	/// What happens at the edge of the playfield.
+	pub border: Border,
+	/// Number of snakes on the playfield, one per player. The level needs to have a start for each of them.
+	pub snakes: u8,
+	/// Number of bananas on the playfield at the same time.
+	pub bananas: u8,
+	/// Number of ticks it takes for a new banana to show up after one got eaten or went away.
+	pub banana_spawn_delay: u32,
+	pub yellow_banana: BananaEffect,
+	pub red_banana: BananaEffect,
+	pub cyan_banana: BananaEffect,
+}
+
+impl Default for Rules {
+	fn default() -> Self {
+		Self {
+			mode: Mode::Classic,
+			start_speed: 3.0,
+			speed_curve: SpeedCurve::Linear,
+			max_speed: f32::INFINITY,
+			border: Border::Wrap,
+			snakes: 1,
+			bananas: 1,
+			banana_spawn_delay: 0,
+			yellow_banana: BananaEffect::CLASSIC,
+			red_banana: BananaEffect {
+				points: 5,
+				growth: 3,
+				speed_change: 0.2,
+				lifetime: 60,
+			},
+			cyan_banana: BananaEffect {
+				points: 10,
+				growth: 1,
+				speed_change: -0.5,
+				lifetime: 40,
+			},
+		}
+	}
+}
+
+// Rules are encoded as a list of tagged fields ending with `TAG_END`, so that adding a rule doesn't break the files
+// that were written before it existed. Fields missing from a file keep their default value.
+const TAG_END: u8 = 0;
+const TAG_MODE: u8 = 1;
+const TAG_START_SPEED: u8 = 2;
+const TAG_SPEED_CURVE: u8 = 3;
+const TAG_MAX_SPEED: u8 = 4;
+const TAG_BORDER: u8 = 5;
+const TAG_SNAKES: u8 = 6;
+const TAG_BANANAS: u8 = 7;
+const TAG_BANANA_SPAWN_DELAY: u8 = 8;
+const TAG_YELLOW_BANANA: u8 = 9;
+const TAG_RED_BANANA: u8 = 10;
+const TAG_CYAN_BANANA: u8 = 11;
+
+impl Rules {
+	pub fn banana_effect(&self, banana: Banana) -> BananaEffect {
+		match banana {
+			Banana::Yellow => self.yellow_banana,
+			Banana::Red => self.red_banana,
+			Banana::Cyan => self.cyan_banana,
+		}
+	}
+
+	/// The speed after a banana got eaten, `bananas_eaten` being how many bananas all the snakes ate so far, that one
+	/// included.
+	///
+	/// `pending_speed_change` holds the speed changes of the bananas eaten since the speed last changed, that one
+	/// included. Whatever gets applied is taken out of it.
+	pub fn next_speed(&self, speed: f32, pending_speed_change: &mut f32, bananas_eaten: u32) -> f32 {
+		let speed_change = match self.speed_curve {
+			SpeedCurve::Linear => std::mem::take(pending_speed_change),
+			SpeedCurve::Stepped(bananas) => match bananas_eaten % bananas.max(1) {
+				0 => std::mem::take(pending_speed_change),
+				_ => 0.0,
+			},
+			SpeedCurve::Capped => {
+				let speed_change = std::mem::take(pending_speed_change);
+				match speed_change > 0.0 && self.max_speed.is_finite() {
+					true => {
+						let room = (self.max_speed - speed) / (self.max_speed - self.start_speed).max(f32::EPSILON);
+						speed_change * room.clamp(0.0, 1.0)
+					}
+					false => speed_change,
+				}
+			}
+		};
+
+		(speed + speed_change)
+			.min(self.max_speed)
+			.max(self.start_speed)
+			.clamp(MIN_SPEED, MAX_SPEED)
+	}
+
+	pub fn encode(&self, encoder: &mut Encoder) {
+		encoder.u8(TAG_MODE);
+		encoder.u8(self.mode as u8);
+
+		encoder.u8(TAG_START_SPEED);
+		encoder.f32(self.start_speed);
+
+		encoder.u8(TAG_SPEED_CURVE);
+		match self.speed_curve {
+			SpeedCurve::Linear => encoder.u8(0),
+			SpeedCurve::Stepped(bananas) => {
+				encoder.u8(1);
+				encoder.varint(bananas as u64);
+			}
+			SpeedCurve::Capped => encoder.u8(2),
+		}
+
+		encoder.u8(TAG_MAX_SPEED);
+		encoder.f32(self.max_speed);
+
+		encoder.u8(TAG_BORDER);
+		encoder.u8(match self.border {
+			Border::Wrap => 0,
+			Border::Solid => 1,
+		});
+
+		encoder.u8(TAG_SNAKES);
+		encoder.u8(self.snakes);
+
+		encoder.u8(TAG_BANANAS);
+		encoder.u8(self.bananas);
+
+		encoder.u8(TAG_BANANA_SPAWN_DELAY);
+		encoder.varint(self.banana_spawn_delay as u64);
+
+		for (tag, effect) in [
+			(TAG_YELLOW_BANANA, &self.yellow_banana),
+			(TAG_RED_BANANA, &self.red_banana),
+			(TAG_CYAN_BANANA, &self.cyan_banana),
+		] {
+			encoder.u8(tag);
+			encoder.varint(effect.points as u64);
+			encoder.varint(effect.growth as u64);
+			encoder.f32(effect.speed_change);
+			encoder.varint(effect.lifetime as u64);
+		}
+
+		encoder.u8(TAG_END);
+	}
+
+	pub fn decode(decoder: &mut Decoder) -> Result<Self, DecodeError> {
+		let mut rules = Self::default();
+
+		loop {
+			match decoder.u8()? {
+				TAG_END => break,
+				TAG_MODE => {
+					rules.mode = *(Mode::ALL.get(decoder.u8()? as usize)).ok_or(DecodeError::Invalid("mode"))?
+				}
+				TAG_START_SPEED => rules.start_speed = decoder.f32()?,
+				TAG_SPEED_CURVE => {
+					rules.speed_curve = match decoder.u8()? {
+						0 => SpeedCurve::Linear,
+						1 => match decoder.varint()? {
+							bananas @ 1..=0xffff => SpeedCurve::Stepped(bananas as u32),
+							_ => return Err(DecodeError::Invalid("speed curve")),
+						},
+						2 => SpeedCurve::Capped,
+						_ => return Err(DecodeError::Invalid("speed curve")),
+					}
+				}
+				TAG_MAX_SPEED => rules.max_speed = decoder.f32()?,
+				TAG_BORDER => {
+					rules.border = match decoder.u8()? {
+						0 => Border::Wrap,
+						1 => Border::Solid,
+						_ => return Err(DecodeError::Invalid("border rule")),
+					}
+				}
+				TAG_SNAKES => rules.snakes = decoder.u8()?,
+				TAG_BANANAS => rules.bananas = decoder.u8()?,
+				TAG_BANANA_SPAWN_DELAY => {
+					rules.banana_spawn_delay =
+						(decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("banana spawn delay"))?
+				}
+				TAG_YELLOW_BANANA => rules.yellow_banana = decode_banana_effect(decoder)?,
+				TAG_RED_BANANA => rules.red_banana = decode_banana_effect(decoder)?,
+				TAG_CYAN_BANANA => rules.cyan_banana = decode_banana_effect(decoder)?,
+				_ => return Err(DecodeError::Invalid("rule")),
+			}
+		}
+
+		let speeds_ok = (MIN_SPEED..=MAX_SPEED).contains(&rules.start_speed) && rules.max_speed >= rules.start_speed;
+		if !(speeds_ok && (1..=MAX_SNAKES as u8).contains(&rules.snakes) && rules.bananas > 0) {
+			return Err(DecodeError::Invalid("rules"));
+		}
+
+		Ok(rules)
+	}
+}
+
+fn decode_banana_effect(decoder: &mut Decoder) -> Result<BananaEffect, DecodeError> {
+	let effect = BananaEffect {
+		points: (decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("banana points"))?,
+		growth: (decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("banana growth"))?,
+		speed_change: decoder.f32()?,
+		lifetime: (decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("banana lifetime"))?,
+	};
+
+	if !(-MAX_SPEED..=MAX_SPEED).contains(&effect.speed_change) {
+		return Err(DecodeError::Invalid("banana speed change"));
+	}
+
+	Ok(effect)
+}
+
+/// Presets of how fast the game goes and how quickly it speeds up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Difficulty {
+	Easy,
+	#[default]
+	Normal,
+	Hard,
+	Insane,
+}
+
+impl Difficulty {
+	pub const ALL: [Self; 4] = [Self::Easy, Self::Normal, Self::Hard, Self::Insane];
+
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Easy => "Easy",
+			Self::Normal => "Normal",
+			Self::Hard => "Hard",
+			Self::Insane => "Insane",
+		}
+	}
+
+	/// The difficulty after this one, going back to the easiest after the hardest.
+	pub fn next(self) -> Self {
+		Self::ALL[(self as usize + 1) % Self::ALL.len()]
+	}
+
+	/// Start speed, speed curve and max speed of the preset.
+	const fn speeds(self) -> (f32, SpeedCurve, f32) {
+		match self {
+			Self::Easy => (2.0, SpeedCurve::Stepped(5), 5.0),
+			Self::Normal => (3.0, SpeedCurve::Linear, f32::INFINITY),
+			Self::Hard => (4.0, SpeedCurve::Capped, 9.0),
+			Self::Insane => (5.5, SpeedCurve::Linear, f32::INFINITY),
+		}
+	}
+
+	/// Sets the speeds of the rules to the ones of the preset. The other rules stay as they are.
+	pub fn apply(self, rules: &mut Rules) {
+		(rules.start_speed, rules.speed_curve, rules.max_speed) = self.speeds();
+	}
+
+	/// The preset the speeds of the rules come from, if they come from one.
+	pub fn of(rules: &Rules) -> Option<Self> {
+		(Self::ALL.into_iter())
+			.find(|difficulty| difficulty.speeds() == (rules.start_speed, rules.speed_curve, rules.max_speed))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stepped_speed_changes_by_the_whole_step_at_once() {
+		let mut rules = Rules::default();
+		Difficulty::Easy.apply(&mut rules);
+		assert_eq!(rules.speed_curve, SpeedCurve::Stepped(5));
+
+		let mut speed = rules.max_speed;
+		let mut pending_speed_change = 0.0;
+		let bananas = [Banana::Cyan, Banana::Cyan, Banana::Cyan, Banana::Cyan, Banana::Yellow];
+		for (i, banana) in bananas.into_iter().enumerate() {
+			assert_eq!(speed, rules.max_speed);
+
+			pending_speed_change += rules.banana_effect(banana).speed_change;
+			speed = rules.next_speed(speed, &mut pending_speed_change, i as u32 + 1);
+		}
+
+		assert!((speed - (rules.max_speed - 1.9)).abs() < 1e-5, "speed {speed}");
+		assert_eq!(pending_speed_change, 0.0);
+	}
+
+	#[test]
+	fn rules_round_trip() {
+		for difficulty in Difficulty::ALL {
+			let mut rules = Rules {
+				mode: Mode::BananaGoal,
+				border: Border::Solid,
+				snakes: 2,
+				bananas: 3,
+				banana_spawn_delay: 10,
+				..Rules::default()
+			};
+			difficulty.apply(&mut rules);
+
+			let mut encoder = Encoder::new(b"TEST", 1);
+			rules.encode(&mut encoder);
+			let data = encoder.finish();
+
+			let (mut decoder, _) = Decoder::new(&data, b"TEST").unwrap();
+			assert_eq!(Rules::decode(&mut decoder).unwrap(), rules);
+			decoder.finish().unwrap();
+		}
+	}
+}
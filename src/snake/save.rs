@@ -0,0 +1,426 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::codec::{DecodeError, Decoder, Encoder};
+use super::level::Level;
+use super::replay::{decode_inputs, encode_inputs};
+use super::rules::{Rules, MAX_SPEED, MIN_SPEED};
+use super::{DeathCause, Direction, PlacedBanana, Slot, SnaekRng, Snake, SnakeGame, Tile, INPUT_QUEUE_LEN, NOT_FREE};
+use crate::math::pos::{pos, Pos};
+use crate::storage::Stored;
+
+const SAVE_MAGIC: &[u8] = b"SNKS";
+const SAVE_VERSION: u8 = 1;
+
+/// Bits of a [`Slot`] that don't mean anything yet.
+const SLOT_UNUSED_BITS: u16 = 0xf000;
+
+impl Stored for SnakeGame<SnaekRng> {
+	const WHAT: &'static str = "saved game";
+
+	/// Serializes the whole state of the game, so that it can carry on later exactly where it left off.
+	///
+	/// # Format
+	///
+	/// ```ignore
+	/// "SNKS" version:u8 (has_seed:u8 seed:u64?) rng_state:u64 level rules
+	/// slot:u16* free_slot_count:varint free_slot:u16* banana_count:varint (x:u16 y:u16 despawn_tick:varint)*
+	/// banana_spawn_count:varint banana_spawn_tick:varint* snake_count:u8 snake*
+	/// speed:f32 pending_speed_change:f32 won:u8 paused:u8 rewinds:varint rewound_from:varint assisted:u8 ticks:varint tick_time_ns:varint duration_ns:varint inputs
+	/// checksum:u64
+	/// ```
+	///
+	/// A dead snake ends with the slot it crashed into.
+	///
+	/// Bananas are listed from oldest to newest, with a despawn tick of 0 for the ones that stay forever and the tick
+	/// plus one for the others.
+	///
+	/// Inputs are stored like in a [`Replay`](super::Replay), so that the replay of a resumed game still covers it
+	/// from the start. The checksum at the end catches files that got corrupted in ways that would still decode fine.
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut encoder = Encoder::new(SAVE_MAGIC, SAVE_VERSION);
+
+		match self.seed {
+			Some(seed) => {
+				encoder.u8(1);
+				encoder.u64(seed);
+			}
+			None => encoder.u8(0),
+		}
+		encoder.u64(self.rng.state());
+		self.level.encode(&mut encoder);
+		self.rules.encode(&mut encoder);
+
+		for slot in self.playfield.iter() {
+			encoder.u16(slot.0);
+		}
+
+		// The order of the free slots decides where the next banana goes, so it has to stay the same.
+		encoder.varint(self.free_slots.len() as u64);
+		for &slot_index in &self.free_slots {
+			encoder.u16(slot_index);
+		}
+
+		encoder.varint(self.bananas.len() as u64);
+		for banana in &self.bananas {
+			encode_pos(&mut encoder, banana.pos);
+			encoder.varint(banana.despawn_tick.map_or(0, |tick| tick + 1));
+		}
+
+		encoder.varint(self.banana_spawns.len() as u64);
+		for &tick in &self.banana_spawns {
+			encoder.varint(tick);
+		}
+
+		encoder.u8(self.snakes.len() as u8);
+		for snake in &self.snakes {
+			encode_pos(&mut encoder, snake.head);
+			encode_pos(&mut encoder, snake.tail);
+			encoder.varint(snake.len as u64);
+			encoder.u8(snake.direction as u8);
+
+			encoder.u8(snake.input_queue.len() as u8);
+			for &direction in &snake.input_queue {
+				encoder.u8(direction as u8);
+			}
+
+			encoder.varint(snake.score as u64);
+			encoder.varint(snake.bananas_eaten as u64);
+			encoder.varint(snake.pending_growth as u64);
+			encoder.u8(snake.ate_banana as u8);
+			encoder.u8(snake.death_cause.map_or(0, |cause| cause as u8 + 1));
+			if let Some(death_pos) = snake.death_pos {
+				encoder.u16(death_pos.x as u16);
+				encoder.u16(death_pos.y as u16);
+			}
+		}
+
+		encoder.f32(self.speed);
+		encoder.f32(self.pending_speed_change);
+		encoder.u8(self.won as u8);
+		encoder.u8(self.paused as u8);
+		encoder.varint(self.rewinds as u64);
+		encoder.varint(self.rewound_from);
+		encoder.u8(self.assisted as u8);
+		encoder.varint(self.ticks);
+		encoder.varint(self.tick_time.as_nanos() as u64);
+		encoder.varint(self.duration.as_nanos() as u64);
+		encode_inputs(&mut encoder, &self.inputs);
+		encoder.checksum();
+
+		encoder.finish()
+	}
+
+	fn from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+		let (mut decoder, version) = Decoder::new(data, SAVE_MAGIC)?;
+
+		if version != SAVE_VERSION {
+			return Err(DecodeError::UnsupportedVersion(version));
+		}
+
+		let seed = match decoder.u8()? {
+			0 => None,
+			1 => Some(decoder.u64()?),
+			_ => return Err(DecodeError::Invalid("seed")),
+		};
+		let rng_state = decoder.u64()?;
+
+		let level = Level::decode(&mut decoder)?;
+		let rules = Rules::decode(&mut decoder)?;
+		if rules.snakes as usize > level.starts.len() {
+			return Err(DecodeError::Invalid("snake count"));
+		}
+
+		// Starting a game places bananas, which draws from the RNG and shows up in the events. Both get put back
+		// right after.
+		let mut game = Self::with_rng(level, rules, SnaekRng::new(rng_state));
+		game.rng = SnaekRng::new(rng_state);
+		game.seed = seed;
+		game.events.clear();
+
+		for (i, slot) in game.playfield.iter_mut().enumerate() {
+			let bits = decoder.u16()?;
+			if bits & SLOT_UNUSED_BITS != 0 || (bits >> 8) & 0b11 > 1 {
+				return Err(DecodeError::Invalid("slot"));
+			}
+
+			*slot = Slot(bits);
+			if slot.tile() != game.level.tiles[i] {
+				return Err(DecodeError::Invalid("slot tile"));
+			}
+		}
+
+		game.free_slots.clear();
+		game.free_slot_indices.fill(NOT_FREE);
+		let free_slot_count = decoder.varint()?;
+		for i in 0..free_slot_count {
+			let slot_index = decoder.u16()?;
+
+			let is_free = (game.playfield.get(slot_index as usize))
+				.is_some_and(|slot| slot.tile() == Tile::Floor && !slot.has_snake() && slot.banana().is_none());
+			if !is_free || game.free_slot_indices[slot_index as usize] != NOT_FREE {
+				return Err(DecodeError::Invalid("free slot"));
+			}
+
+			game.free_slot_indices[slot_index as usize] = i as u16;
+			game.free_slots.push(slot_index);
+		}
+
+		let actually_free = (game.playfield.iter())
+			.filter(|slot| slot.tile() == Tile::Floor && !slot.has_snake() && slot.banana().is_none())
+			.count();
+		if actually_free != game.free_slots.len() {
+			return Err(DecodeError::Invalid("free slots"));
+		}
+
+		game.bananas.clear();
+		game.banana_spawns.clear();
+		let banana_count = decoder.varint()?;
+		for _ in 0..banana_count {
+			let pos = decode_pos(&mut decoder, &game)?;
+			let kind = (game.slot_at(pos).banana()).ok_or(DecodeError::Invalid("banana"))?;
+			if game.bananas.iter().any(|banana| banana.pos == pos) {
+				return Err(DecodeError::Invalid("banana"));
+			}
+
+			let despawn_tick = decoder.varint()?.checked_sub(1);
+			game.bananas.push(PlacedBanana {
+				kind,
+				pos,
+				despawn_tick,
+			});
+		}
+
+		let banana_spawn_count = decoder.varint()?;
+		for _ in 0..banana_spawn_count {
+			let tick = decoder.varint()?;
+			if game.banana_spawns.back().is_some_and(|&last| last > tick) {
+				return Err(DecodeError::Invalid("banana spawn"));
+			}
+			game.banana_spawns.push_back(tick);
+		}
+
+		if game.playfield.iter().filter(|slot| slot.banana().is_some()).count() != game.bananas.len() {
+			return Err(DecodeError::Invalid("bananas"));
+		}
+
+		let snake_count = decoder.u8()?;
+		if snake_count != game.rules.snakes {
+			return Err(DecodeError::Invalid("snake count"));
+		}
+
+		game.snakes.clear();
+		for _ in 0..snake_count {
+			let head = decode_pos(&mut decoder, &game)?;
+			let tail = decode_pos(&mut decoder, &game)?;
+			let len = decoder.varint()?;
+			let direction = decode_direction(&mut decoder)?;
+
+			let input_queue_len = decoder.u8()? as usize;
+			if input_queue_len > INPUT_QUEUE_LEN {
+				return Err(DecodeError::Invalid("input queue"));
+			}
+
+			let mut input_queue = VecDeque::with_capacity(INPUT_QUEUE_LEN);
+			for _ in 0..input_queue_len {
+				input_queue.push_back(decode_direction(&mut decoder)?);
+			}
+
+			let mut snake = Snake {
+				head,
+				tail,
+				len: (len.try_into()).map_err(|_| DecodeError::Invalid("snake length"))?,
+				direction,
+				input_queue,
+				score: decode_u32(&mut decoder)?,
+				bananas_eaten: decode_u32(&mut decoder)?,
+				pending_growth: decode_u32(&mut decoder)?,
+				ate_banana: decode_bool(&mut decoder)?,
+				death_cause: match decoder.u8()? {
+					0 => None,
+					1 => Some(DeathCause::SelfCollision),
+					2 => Some(DeathCause::Wall),
+					3 => Some(DeathCause::OtherSnake),
+					_ => return Err(DecodeError::Invalid("death cause")),
+				},
+				death_pos: None,
+			};
+
+			if snake.is_dead() {
+				snake.death_pos = Some(decode_death_pos(&mut decoder, &game)?);
+			}
+
+			game.snakes.push(snake);
+		}
+
+		game.check_snakes()?;
+
+		game.speed = decoder.f32()?;
+		if !(MIN_SPEED..=MAX_SPEED).contains(&game.speed) {
+			return Err(DecodeError::Invalid("speed"));
+		}
+
+		game.pending_speed_change = decoder.f32()?;
+		if !game.pending_speed_change.is_finite() {
+			return Err(DecodeError::Invalid("pending speed change"));
+		}
+
+		game.won = decode_bool(&mut decoder)?;
+		game.paused = decode_bool(&mut decoder)?;
+		game.rewinds = decode_u32(&mut decoder)?;
+		game.rewound_from = decoder.varint()?;
+		game.assisted = decode_bool(&mut decoder)?;
+		game.ticks = decoder.varint()?;
+		game.tick_time = Duration::from_nanos(decoder.varint()?);
+		game.duration = Duration::from_nanos(decoder.varint()?);
+		game.inputs = decode_inputs(&mut decoder, game.ticks, game.rules.snakes)?;
+
+		decoder.checksum()?;
+		decoder.finish()?;
+
+		Ok(game)
+	}
+}
+
+impl SnakeGame<SnaekRng> {
+	/// Makes sure each snake goes from its tail to its head through slots that belong to it, and that no other slot
+	/// has a snake on it.
+	fn check_snakes(&self) -> Result<(), DecodeError> {
+		let mut visited = vec![false; self.playfield.len()];
+		let mut snake_slots = 0;
+
+		for (i, snake) in self.snakes.iter().enumerate() {
+			if snake.len < 2 || snake.len as usize > self.playfield.len() {
+				return Err(DecodeError::Invalid("snake length"));
+			}
+
+			let mut pos = snake.tail;
+			for remaining in (0..snake.len).rev() {
+				let slot = self.slot_at(pos);
+				let seen = std::mem::replace(&mut visited[self.slot_index(pos)], true);
+				if seen || !slot.has_snake() || slot.snake_index() as usize != i {
+					return Err(DecodeError::Invalid("snake"));
+				}
+
+				if remaining == 0 {
+					break;
+				}
+				pos = self.wrap_pos(self.next_at(pos));
+			}
+
+			if pos != snake.head {
+				return Err(DecodeError::Invalid("snake"));
+			}
+
+			snake_slots += snake.len as usize;
+		}
+
+		if self.playfield.iter().filter(|slot| slot.has_snake()).count() != snake_slots {
+			return Err(DecodeError::Invalid("snake"));
+		}
+
+		Ok(())
+	}
+}
+
+fn encode_pos(encoder: &mut Encoder, p: Pos) {
+	encoder.u16(p.x as u16);
+	encoder.u16(p.y as u16);
+}
+
+fn decode_pos<R>(decoder: &mut Decoder, game: &SnakeGame<R>) -> Result<Pos, DecodeError> {
+	let (x, y) = (decoder.u16()?, decoder.u16()?);
+	if x >= game.size.w || y >= game.size.h {
+		return Err(DecodeError::Invalid("position"));
+	}
+
+	Ok(pos(x as i16, y as i16))
+}
+
+/// Like [`decode_pos`], but the slot can be right outside the playfield, where the solid border is.
+fn decode_death_pos<R>(decoder: &mut Decoder, game: &SnakeGame<R>) -> Result<Pos, DecodeError> {
+	let (x, y) = (decoder.u16()? as i16, decoder.u16()? as i16);
+	if !((-1..=game.size.w as i16).contains(&x) && (-1..=game.size.h as i16).contains(&y)) {
+		return Err(DecodeError::Invalid("death position"));
+	}
+
+	Ok(pos(x, y))
+}
+
+fn decode_direction(decoder: &mut Decoder) -> Result<Direction, DecodeError> {
+	match decoder.u8()? {
+		bits @ 0..=3 => Ok(Direction::from_bits(bits)),
+		_ => Err(DecodeError::Invalid("direction")),
+	}
+}
+
+fn decode_u32(decoder: &mut Decoder) -> Result<u32, DecodeError> {
+	(decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("number"))
+}
+
+fn decode_bool(decoder: &mut Decoder) -> Result<bool, DecodeError> {
+	match decoder.u8()? {
+		0 => Ok(false),
+		1 => Ok(true),
+		_ => Err(DecodeError::Invalid("flag")),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::snake::{bot_by_name, builtin_levels, Rules};
+
+	/// A game a bot played for a while, so that there's a bit of everything to save.
+	fn played_game() -> SnakeGame {
+		let mut game = SnakeGame::new(builtin_levels()[0].clone(), Rules::default(), 42);
+		let mut bot = bot_by_name("greedy").unwrap();
+		while !game.is_over() && game.ticks() < 300 {
+			let direction = bot.next_direction(&game, 0);
+			game.change_direction(0, direction);
+			game.update();
+		}
+		game
+	}
+
+	#[test]
+	fn save_round_trip() {
+		let mut game = played_game();
+		let data = game.to_bytes();
+
+		let mut loaded = SnakeGame::from_bytes(&data).unwrap();
+		assert_eq!(loaded.to_bytes(), data);
+		assert_eq!(loaded.state_hash(), game.state_hash());
+
+		// the loaded game carries on exactly like the original one
+		let mut bot = bot_by_name("greedy").unwrap();
+		while !game.is_over() && game.ticks() < 600 {
+			let direction = bot.next_direction(&game, 0);
+			game.change_direction(0, direction);
+			loaded.change_direction(0, direction);
+			game.update();
+			loaded.update();
+		}
+		assert_eq!(loaded.state_hash(), game.state_hash());
+	}
+
+	#[test]
+	fn truncated_save_is_rejected() {
+		let data = played_game().to_bytes();
+
+		for len in 0..data.len() {
+			assert!(SnakeGame::from_bytes(&data[..len]).is_err(), "length {len}");
+		}
+	}
+
+	#[test]
+	fn corrupted_save_is_rejected() {
+		let data = played_game().to_bytes();
+
+		for bit in 0..data.len() * 8 {
+			let mut corrupted = data.clone();
+			corrupted[bit / 8] ^= 1 << (bit % 8);
+			assert!(SnakeGame::from_bytes(&corrupted).is_err(), "bit {bit}");
+		}
+	}
+}
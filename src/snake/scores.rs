@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use super::codec::{DecodeError, Decoder, Encoder};
+use super::rules::{Border, Difficulty};
+use crate::date::Date;
+use crate::math::size::{size, Size};
+use crate::storage::Stored;
+
+const SCORES_MAGIC: &[u8] = b"SNKH";
+const SCORES_VERSION: u8 = 2;
+
+/// Number of scores kept in the table. That's as many as fit on the playfield, along with the details of one of them.
+pub const MAX_HIGH_SCORES: usize = 6;
+
+/// Maximum length of a name in the table, in characters.
+pub const MAX_NAME_LEN: usize = 8;
+
+/// One finished game in the [`HighScores`] table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighScore {
+	pub name: String,
+	pub score: u32,
+	pub duration: Duration,
+	/// Size of the playfield the game was played on.
+	pub size: Size,
+	pub date: Date,
+	pub difficulty: Difficulty,
+	/// Name of the level the game was played on.
+	pub level: String,
+	pub border: Border,
+}
+
+/// The best scores ever made on this computer, best first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HighScores {
+	entries: Vec<HighScore>,
+}
+
+impl HighScores {
+	pub fn entries(&self) -> &[HighScore] {
+		&self.entries
+	}
+
+	/// Whether a game with this score would make it into the table.
+	pub fn qualifies(&self, score: u32) -> bool {
+		score > 0 && (self.entries.len() < MAX_HIGH_SCORES || self.entries.iter().any(|entry| score > entry.score))
+	}
+
+	/// Adds a score to the table, pushing the worst one out if it's full. Returns its rank, if it made it in.
+	///
+	/// A new score ranks below older ones that are just as good.
+	pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+		if !self.qualifies(entry.score) {
+			return None;
+		}
+
+		let rank = self.entries.partition_point(|other| other.score >= entry.score);
+		self.entries.insert(rank, entry);
+		self.entries.truncate(MAX_HIGH_SCORES);
+
+		Some(rank)
+	}
+}
+
+impl Stored for HighScores {
+	const WHAT: &'static str = "high scores";
+
+	/// Serializes the table.
+	///
+	/// # Format
+	///
+	/// ```ignore
+	/// "SNKH" version:u8 entry_count:u8
+	/// (name_len:varint name:u8* score:varint duration_ms:varint w:u16 h:u16 days_since_1970:varint difficulty:u8
+	///  level_len:varint level:u8* border:u8)*
+	/// checksum:u64
+	/// ```
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut encoder = Encoder::new(SCORES_MAGIC, SCORES_VERSION);
+
+		encoder.u8(self.entries.len() as u8);
+		for entry in &self.entries {
+			encoder.varint(entry.name.len() as u64);
+			encoder.bytes(entry.name.as_bytes());
+			encoder.varint(entry.score as u64);
+			encoder.varint(entry.duration.as_millis() as u64);
+			encoder.u16(entry.size.w);
+			encoder.u16(entry.size.h);
+			encoder.varint(entry.date.days().max(0) as u64);
+			encoder.u8(entry.difficulty as u8);
+			encoder.varint(entry.level.len() as u64);
+			encoder.bytes(entry.level.as_bytes());
+			encoder.u8(match entry.border {
+				Border::Wrap => 0,
+				Border::Solid => 1,
+			});
+		}
+
+		encoder.checksum();
+		encoder.finish()
+	}
+
+	fn from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+		let (mut decoder, version) = Decoder::new(data, SCORES_MAGIC)?;
+
+		if version != SCORES_VERSION {
+			return Err(DecodeError::UnsupportedVersion(version));
+		}
+
+		let entry_count = decoder.u8()? as usize;
+		if entry_count > MAX_HIGH_SCORES {
+			return Err(DecodeError::Invalid("score count"));
+		}
+
+		let mut entries: Vec<HighScore> = Vec::with_capacity(entry_count);
+		for _ in 0..entry_count {
+			let name_len = decoder.varint()? as usize;
+			let name = (String::from_utf8(decoder.bytes(name_len)?.to_vec()))
+				.ok()
+				.filter(|name| name.chars().count() <= MAX_NAME_LEN)
+				.ok_or(DecodeError::Invalid("name"))?;
+
+			let score = (decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("score"))?;
+			if entries.last().is_some_and(|last| last.score < score) {
+				return Err(DecodeError::Invalid("score order"));
+			}
+
+			let duration = Duration::from_millis(decoder.varint()?);
+			let size = size(decoder.u16()?, decoder.u16()?);
+			let date = Date::from_days((decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("date"))?);
+
+			let difficulty =
+				*(Difficulty::ALL.get(decoder.u8()? as usize)).ok_or(DecodeError::Invalid("difficulty"))?;
+
+			let level_len = decoder.varint()? as usize;
+			let level =
+				String::from_utf8(decoder.bytes(level_len)?.to_vec()).map_err(|_| DecodeError::Invalid("level"))?;
+
+			let border = match decoder.u8()? {
+				0 => Border::Wrap,
+				1 => Border::Solid,
+				_ => return Err(DecodeError::Invalid("border")),
+			};
+
+			entries.push(HighScore {
+				name,
+				score,
+				duration,
+				size,
+				date,
+				difficulty,
+				level,
+				border,
+			});
+		}
+
+		decoder.checksum()?;
+		decoder.finish()?;
+
+		Ok(Self { entries })
+	}
+}
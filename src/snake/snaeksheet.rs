@@ -25,6 +25,9 @@ pub struct SnaekSheet {
 	/// Cyan banana
 	pub banana_cyan: Sprite,
 
+	/// Wall tile
+	pub wall: Sprite,
+
 	/// playfield box
 	pub box_playfield: NineSlicingSprite,
 	/// big carved box
@@ -88,6 +91,8 @@ pub fn snaek_sheet() -> SnaekSheet {
 		banana_red:         Sprite::new(Rect::from_xywh( 14,   7,  7,  7)),
 		banana_cyan:        Sprite::new(Rect::from_xywh( 21,   7,  7,  7)),
 
+		wall:               Sprite::new(Rect::from_xywh(  0,  34,  7,  7)),
+
 		box_playfield:      NineSlicingSprite::new(Rect::from_xywh(  9,  14,  9,  9),  4,  5,  4,  5),
 		box_big_carved:     NineSlicingSprite::new(Rect::from_xywh( 18,  14,  5,  5),  2,  3,  2,  3),
 		box_num_display:    NineSlicingSprite::new(Rect::from_xywh( 23,  14,  3,  3),  1,  2,  1,  2),
@@ -0,0 +1,259 @@
+use std::time::Duration;
+
+use rand::{Rng, SeedableRng};
+
+use super::codec::{DecodeError, Decoder, Encoder};
+use super::mode::Mode;
+use super::{Banana, GameEvent, SnakeGame};
+use crate::storage::Stored;
+
+const STATS_MAGIC: &[u8] = b"SNKT";
+const STATS_VERSION: u8 = 1;
+
+/// Length a snake has to reach for [`Achievement::LongBoi`].
+const LONG_SNAKE_LEN: u32 = 30;
+
+/// Speed the snakes have to reach for [`Achievement::Zoomies`], in tiles per second.
+const FAST_SPEED: f32 = 8.0;
+
+/// Score to reach before the time runs out for [`Achievement::Clockwork`].
+const TIME_ATTACK_SCORE: u32 = 50;
+
+/// Number of games to play for [`Achievement::Regular`].
+const REGULAR_GAMES: u32 = 50;
+
+/// Time to play for [`Achievement::Marathon`].
+const MARATHON_PLAY_TIME: Duration = Duration::from_secs(60 * 60);
+
+/// Milestones to unlock by playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+	FirstBite,
+	Spicy,
+	BrainFreeze,
+	LongBoi,
+	Zoomies,
+	FullHouse,
+	OnTarget,
+	Clockwork,
+	Regular,
+	Marathon,
+}
+
+impl Achievement {
+	pub const ALL: [Self; 10] = [
+		Self::FirstBite,
+		Self::Spicy,
+		Self::BrainFreeze,
+		Self::LongBoi,
+		Self::Zoomies,
+		Self::FullHouse,
+		Self::OnTarget,
+		Self::Clockwork,
+		Self::Regular,
+		Self::Marathon,
+	];
+
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::FirstBite => "First bite",
+			Self::Spicy => "Spicy",
+			Self::BrainFreeze => "Brain freeze",
+			Self::LongBoi => "Long boi",
+			Self::Zoomies => "Zoomies",
+			Self::FullHouse => "Full house",
+			Self::OnTarget => "On target",
+			Self::Clockwork => "Clockwork",
+			Self::Regular => "Regular",
+			Self::Marathon => "Marathon",
+		}
+	}
+
+	/// What it takes to unlock the achievement.
+	pub const fn description(self) -> &'static str {
+		match self {
+			Self::FirstBite => "Eat a banana",
+			Self::Spicy => "Eat a red one",
+			Self::BrainFreeze => "Eat a cyan one",
+			Self::LongBoi => "Grow to 30",
+			Self::Zoomies => "Go 8 tiles/s",
+			Self::FullHouse => "Fill the board",
+			Self::OnTarget => "Win banana goal",
+			Self::Clockwork => "50 in time attack",
+			Self::Regular => "Play 50 games",
+			Self::Marathon => "Play for an hour",
+		}
+	}
+
+	const fn bit(self) -> u32 {
+		1 << self as u32
+	}
+}
+
+/// Everything the player did over all the games they played on this computer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+	pub games_played: u32,
+	/// In-game time spent playing, pauses excluded.
+	pub play_time: Duration,
+	/// Bananas eaten, by kind.
+	pub yellow_bananas: u64,
+	pub red_bananas: u64,
+	pub cyan_bananas: u64,
+	/// Length of the longest snake ever.
+	pub longest_snake: u32,
+	/// Highest speed the snakes ever reached, in tiles per second.
+	pub fastest_speed: f32,
+	/// One bit per unlocked achievement, in the order of [`Achievement::ALL`].
+	achievements: u32,
+}
+
+impl Stats {
+	pub fn has(&self, achievement: Achievement) -> bool {
+		self.achievements & achievement.bit() != 0
+	}
+
+	/// Takes a tick that just ran into account, from the [events](SnakeGame::events) it produced. `tick_duration` is
+	/// how long that tick lasted.
+	///
+	/// Ticks that run again after a rewind were already taken into account the first time, so they're skipped.
+	///
+	/// Returns the achievements that the tick unlocked.
+	pub fn record_tick<R: Rng + SeedableRng>(
+		&mut self,
+		game: &SnakeGame<R>,
+		tick_duration: Duration,
+	) -> Vec<Achievement> {
+		if game.is_replaying() {
+			return Vec::new();
+		}
+
+		let unlocked_before = self.achievements;
+		let mode = game.rules().mode;
+
+		self.play_time += tick_duration;
+		self.fastest_speed = self.fastest_speed.max(game.speed());
+
+		// snakes don't only get long by growing, they can start out long or come back long from a saved game
+		let longest_snake = game.snakes().iter().map(|snake| snake.len()).max().unwrap_or(0);
+		self.longest_snake = self.longest_snake.max(longest_snake);
+
+		for event in game.events() {
+			match *event {
+				GameEvent::AteBanana { kind, .. } => {
+					match kind {
+						Banana::Yellow => self.yellow_bananas += 1,
+						Banana::Red => self.red_bananas += 1,
+						Banana::Cyan => self.cyan_bananas += 1,
+					}
+
+					self.unlock(Achievement::FirstBite);
+					match kind {
+						Banana::Yellow => {}
+						Banana::Red => self.unlock(Achievement::Spicy),
+						Banana::Cyan => self.unlock(Achievement::BrainFreeze),
+					}
+				}
+				GameEvent::Won if game.is_playfield_full() => self.unlock(Achievement::FullHouse),
+				GameEvent::Won if mode == Mode::BananaGoal => self.unlock(Achievement::OnTarget),
+				GameEvent::TimeUp if game.snakes().iter().any(|snake| snake.score() >= TIME_ATTACK_SCORE) => {
+					self.unlock(Achievement::Clockwork)
+				}
+				_ => {}
+			}
+		}
+
+		if self.longest_snake >= LONG_SNAKE_LEN {
+			self.unlock(Achievement::LongBoi);
+		}
+		if self.fastest_speed >= FAST_SPEED {
+			self.unlock(Achievement::Zoomies);
+		}
+		if self.play_time >= MARATHON_PLAY_TIME {
+			self.unlock(Achievement::Marathon);
+		}
+
+		let unlocked = self.achievements & !unlocked_before;
+		(Achievement::ALL.into_iter())
+			.filter(|achievement| unlocked & achievement.bit() != 0)
+			.collect()
+	}
+
+	/// Counts a game the player is moving on from, if it's over and they played it by hand.
+	///
+	/// A game over can still be rewound in practice mode, so a game only really ends once another one replaces it.
+	pub fn record_game<R: Rng + SeedableRng>(&mut self, game: &SnakeGame<R>) {
+		if !game.is_over() || game.is_assisted() {
+			return;
+		}
+
+		self.games_played += 1;
+		if self.games_played >= REGULAR_GAMES {
+			self.unlock(Achievement::Regular);
+		}
+	}
+
+	fn unlock(&mut self, achievement: Achievement) {
+		self.achievements |= achievement.bit();
+	}
+}
+
+impl Stored for Stats {
+	const WHAT: &'static str = "stats";
+
+	/// Serializes the stats.
+	///
+	/// # Format
+	///
+	/// ```ignore
+	/// "SNKT" version:u8 games_played:varint play_time_ms:varint
+	/// yellow_bananas:varint red_bananas:varint cyan_bananas:varint longest_snake:varint fastest_speed:f32
+	/// achievements:varint checksum:u64
+	/// ```
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut encoder = Encoder::new(STATS_MAGIC, STATS_VERSION);
+
+		encoder.varint(self.games_played as u64);
+		encoder.varint(self.play_time.as_millis() as u64);
+		encoder.varint(self.yellow_bananas);
+		encoder.varint(self.red_bananas);
+		encoder.varint(self.cyan_bananas);
+		encoder.varint(self.longest_snake as u64);
+		encoder.f32(self.fastest_speed);
+		encoder.varint(self.achievements as u64);
+
+		encoder.checksum();
+		encoder.finish()
+	}
+
+	fn from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+		let (mut decoder, version) = Decoder::new(data, STATS_MAGIC)?;
+		if version != STATS_VERSION {
+			return Err(DecodeError::UnsupportedVersion(version));
+		}
+
+		let stats = Self {
+			games_played: (decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("games played"))?,
+			play_time: Duration::from_millis(decoder.varint()?),
+			yellow_bananas: decoder.varint()?,
+			red_bananas: decoder.varint()?,
+			cyan_bananas: decoder.varint()?,
+			longest_snake: (decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("longest snake"))?,
+			fastest_speed: decoder.f32()?,
+			achievements: (decoder.varint()?.try_into()).map_err(|_| DecodeError::Invalid("achievements"))?,
+		};
+
+		decoder.checksum()?;
+		decoder.finish()?;
+
+		if !(stats.fastest_speed.is_finite() && stats.fastest_speed >= 0.0) {
+			return Err(DecodeError::Invalid("fastest speed"));
+		}
+
+		if stats.achievements >> Achievement::ALL.len() != 0 {
+			return Err(DecodeError::Invalid("achievements"));
+		}
+
+		Ok(stats)
+	}
+}
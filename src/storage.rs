@@ -0,0 +1,114 @@
+use std::error::Error;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use owo_colors::OwoColorize;
+
+use crate::snake::codec::DecodeError;
+
+/// Something that gets written to a file in a binary format of its own.
+pub trait Stored: Sized {
+	/// What's in the file, for error messages.
+	const WHAT: &'static str;
+
+	fn to_bytes(&self) -> Vec<u8>;
+
+	fn from_bytes(data: &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// Directory where Snaek keeps its files.
+///
+/// That's `$XDG_DATA_HOME/snaek` (or `~/.local/share/snaek`) on Linux, `~/Library/Application Support/snaek` on
+/// macOS and `%APPDATA%\snaek` on Windows.
+pub fn data_dir() -> Option<PathBuf> {
+	let var = |name| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+
+	let base = if cfg!(windows) {
+		var("APPDATA")?
+	} else if cfg!(target_os = "macos") {
+		var("HOME")?.join("Library/Application Support")
+	} else {
+		var("XDG_DATA_HOME").or_else(|| Some(var("HOME")?.join(".local/share")))?
+	};
+
+	Some(base.join("snaek"))
+}
+
+/// Path to a file in the [data directory](data_dir), which gets created if it doesn't exist yet.
+pub fn data_file(name: &str) -> io::Result<PathBuf> {
+	let dir = data_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "couldn't find a data directory"))?;
+	std::fs::create_dir_all(&dir)?;
+	Ok(dir.join(name))
+}
+
+/// Writes the file next to where it goes first, then moves it in place. That way, the old file stays whole if
+/// something goes wrong halfway through.
+pub fn save<T: Stored>(path: impl AsRef<Path>, value: &T) -> io::Result<()> {
+	let path = path.as_ref();
+	let temp_path = with_suffix(path, ".tmp");
+
+	let result = std::fs::write(&temp_path, value.to_bytes()).and_then(|()| std::fs::rename(&temp_path, path));
+	if result.is_err() {
+		let _ = std::fs::remove_file(&temp_path);
+	}
+
+	result
+}
+
+pub fn load<T: Stored>(path: impl AsRef<Path>) -> Result<T, Box<dyn Error>> {
+	let data = std::fs::read(path)?;
+	Ok(T::from_bytes(&data)?)
+}
+
+/// Loads a file from the [data directory](data_dir), if it's there. Whatever goes wrong gets reported and treated
+/// like there's no file.
+///
+/// A file that can't be loaded gets moved out of the way to a `.bak` file rather than left to be overwritten, so
+/// that nothing in it is lost for good.
+pub fn load_data<T: Stored>(name: &str) -> Option<T> {
+	let path = data_file(name).ok().filter(|path| path.exists())?;
+
+	match load(&path) {
+		Ok(value) => Some(value),
+		Err(e) => {
+			eprintln!("{}", format!("Couldn't load the {} :(", T::WHAT).yellow());
+			eprintln!("-> {}", e);
+
+			let backup_path = with_suffix(&path, ".bak");
+			match std::fs::rename(&path, &backup_path) {
+				Ok(()) => eprintln!("-> It was moved to {}", backup_path.display()),
+				Err(e) => eprintln!("-> It couldn't be moved out of the way either: {}", e),
+			}
+
+			None
+		}
+	}
+}
+
+/// Writes a file to the [data directory](data_dir), reporting it if that doesn't work.
+pub fn save_data<T: Stored>(name: &str, value: &T) {
+	if let Err(e) = data_file(name).and_then(|path| save(path, value)) {
+		eprintln!("{}", format!("Couldn't save the {} :(", T::WHAT).yellow());
+		eprintln!("-> {}", e);
+	}
+}
+
+/// The path with `suffix` added at the end of the file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(suffix);
+	PathBuf::from(path)
+}
+
+/// Removes a file from the [data directory](data_dir), if it's there, reporting it if that doesn't work.
+pub fn remove_data<T: Stored>(name: &str) {
+	let result = data_file(name).and_then(|path| match std::fs::remove_file(path) {
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+		result => result,
+	});
+
+	if let Err(e) = result {
+		eprintln!("{}", format!("Couldn't remove the {} :(", T::WHAT).yellow());
+		eprintln!("-> {}", e);
+	}
+}
@@ -96,12 +96,22 @@ pub struct Widget {
 	pressed: bool,
 	start_pressed: bool,
 	clicked: bool,
+	r_pressed: bool,
+	r_clicked: bool,
 
 	// Layout state calculated each frame
 	solved_rect: Rect,
 	solved_min_size: Size,
 }
 
+impl Widget {
+	/// Rectangle the widget occupied in the last solved layout.
+	#[inline]
+	pub const fn solved_rect(&self) -> Rect {
+		self.solved_rect
+	}
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct WidgetReaction {
 	id: WidgetId,
@@ -109,6 +119,7 @@ pub struct WidgetReaction {
 	pressed: bool,
 	start_pressed: bool,
 	clicked: bool,
+	r_clicked: bool,
 }
 
 impl WidgetReaction {
@@ -136,6 +147,12 @@ impl WidgetReaction {
 	pub const fn clicked(&self) -> bool {
 		self.clicked
 	}
+
+	/// Whether the widget got right clicked, which only happens on the frame the button is released.
+	#[inline]
+	pub const fn r_clicked(&self) -> bool {
+		self.r_clicked
+	}
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -513,6 +530,7 @@ impl UiContext {
 					pressed: widget.pressed,
 					start_pressed: widget.start_pressed,
 					clicked: widget.clicked,
+					r_clicked: widget.r_clicked,
 				}
 			}
 			None => {
@@ -535,6 +553,8 @@ impl UiContext {
 					pressed: false,
 					start_pressed: false,
 					clicked: false,
+					r_pressed: false,
+					r_clicked: false,
 
 					solved_rect: Rect::ZERO,
 					solved_min_size: Size::ZERO,
@@ -546,6 +566,7 @@ impl UiContext {
 					pressed: false,
 					start_pressed: false,
 					clicked: false,
+					r_clicked: false,
 				}
 			}
 		}
@@ -691,13 +712,16 @@ impl UiContext {
 		draw_cmds.push(DrawCommand::EndComposite(alphacomp::over));
 	}
 
-	fn react_rec(&mut self, mouse: &Mouse, wid: WidgetId) -> bool {
+	fn react_rec(&mut self, mouse: &Mouse, wid: WidgetId, changed: &mut bool) -> bool {
 		{
 			let mut widget = self.widget_mut(wid);
 			if widget.props.flags.has(WidgetFlags::DISABLED) {
+				*changed |= widget.hovered || widget.pressed || widget.clicked || widget.r_clicked;
 				widget.hovered = false;
 				widget.pressed = false;
 				widget.clicked = false;
+				widget.r_pressed = false;
+				widget.r_clicked = false;
 				return false;
 			}
 		}
@@ -705,7 +729,7 @@ impl UiContext {
 		let mut any_child_hovered = false;
 		let mut child = self.widget(wid).first_child;
 		while let Some(ch) = child {
-			any_child_hovered |= self.react_rec(mouse, ch);
+			any_child_hovered |= self.react_rec(mouse, ch, changed);
 			child = self.widget(ch).next;
 		}
 
@@ -714,6 +738,8 @@ impl UiContext {
 		let can_click = widget.props.flags.has(WidgetFlags::CAN_CLICK);
 
 		let pressed_prev = widget.pressed;
+		let r_pressed_prev = widget.r_pressed;
+		let reaction_prev = (widget.hovered, widget.pressed, widget.clicked, widget.r_clicked);
 		let hovered = !any_child_hovered && widget.solved_rect.contains(mouse.x, mouse.y);
 
 		widget.hovered = can_hover && hovered;
@@ -724,13 +750,24 @@ impl UiContext {
 			};
 		widget.start_pressed = widget.pressed && !pressed_prev;
 		widget.clicked = can_click && hovered && mouse.l_pressed_end() && pressed_prev;
+		widget.r_pressed = can_click
+			&& match hovered {
+				true => mouse.r_pressed_start() || (mouse.r_pressed() && r_pressed_prev),
+				false => mouse.r_pressed() && r_pressed_prev,
+			};
+		widget.r_clicked = can_click && hovered && mouse.r_pressed_end() && r_pressed_prev;
+		*changed |= (widget.hovered, widget.pressed, widget.clicked, widget.r_clicked) != reaction_prev;
 
 		widget.hovered
 	}
 
-	pub fn react(&mut self, mouse: &Mouse) {
+	/// Updates how the widgets react to the mouse. Returns whether any of them reacts differently than before, in
+	/// which case the UI has to be built again for it to show.
+	pub fn react(&mut self, mouse: &Mouse) -> bool {
 		// oh no, not React D:
-		self.react_rec(mouse, Self::ROOT_WIDGET);
+		let mut changed = false;
+		self.react_rec(mouse, Self::ROOT_WIDGET, &mut changed);
+		changed
 	}
 
 	pub fn widget(&self, wid: WidgetId) -> Ref<'_, Widget> {